@@ -0,0 +1,312 @@
+use std::{
+    any::Any,
+    collections::HashMap,
+    future::Future,
+    marker::PhantomData,
+    sync::{Arc, Mutex},
+};
+
+use tokio::{
+    sync::broadcast::{self, error::RecvError},
+    task::AbortHandle,
+};
+use tracing::warn;
+
+use super::{mailbox::WeakMailbox, Actor, ActorError};
+
+const DEFAULT_TOPIC_CAPACITY: usize = 256;
+
+/// Typed name of a topic, events published on it are `T`.
+#[derive(Debug)]
+pub struct Topic<T> {
+    name: &'static str,
+    capacity: usize,
+    _event: PhantomData<fn() -> T>,
+}
+
+impl<T> Topic<T> {
+    pub const fn new(name: &'static str) -> Self {
+        Self::with_capacity(name, DEFAULT_TOPIC_CAPACITY)
+    }
+
+    /// `capacity` is the number of events a subscriber can fall behind before
+    /// its lag policy applies.
+    pub const fn with_capacity(name: &'static str, capacity: usize) -> Self {
+        Topic {
+            name,
+            capacity,
+            _event: PhantomData,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+/// What a subscription does when it falls behind the publishers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Lag {
+    /// Skip the missed events and keep receiving.
+    #[default]
+    Skip,
+    /// End the subscription.
+    Disconnect,
+}
+
+pub struct Subscription<T> {
+    topic: &'static str,
+    receiver: broadcast::Receiver<T>,
+    lag: Lag,
+    missed: u64,
+}
+
+impl<T> std::fmt::Debug for Subscription<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Subscription")
+            .field("topic", &self.topic)
+            .field("lag", &self.lag)
+            .field("missed", &self.missed)
+            .finish()
+    }
+}
+
+impl<T: Clone> Subscription<T> {
+    /// Returns the next event, `None` when the subscription ended.
+    pub async fn recv(&mut self) -> Option<T> {
+        loop {
+            match self.receiver.recv().await {
+                Ok(event) => return Some(event),
+                Err(RecvError::Closed) => return None,
+                Err(RecvError::Lagged(missed)) => {
+                    self.missed += missed;
+                    warn!("subscriber of `{}` missed {} events", self.topic, missed);
+
+                    if self.lag == Lag::Disconnect {
+                        return None;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Number of events skipped because the subscription lagged.
+    pub fn missed(&self) -> u64 {
+        self.missed
+    }
+}
+
+/// Workspace scoped publish and subscribe.
+#[derive(Debug, Clone, Default)]
+pub struct EventBus {
+    topics: Arc<Mutex<HashMap<&'static str, Box<dyn Any + Send>>>>,
+}
+
+impl EventBus {
+    /// Publishes `event` and returns the number of subscriptions it reached.
+    pub fn publish<T: Clone + Send + 'static>(
+        &self,
+        topic: &Topic<T>,
+        event: T,
+    ) -> Result<usize, ActorError> {
+        Ok(self.sender(topic)?.send(event).unwrap_or(0))
+    }
+
+    pub fn subscribe<T: Clone + Send + 'static>(
+        &self,
+        topic: &Topic<T>,
+        lag: Lag,
+    ) -> Result<Subscription<T>, ActorError> {
+        Ok(Subscription {
+            topic: topic.name,
+            receiver: self.sender(topic)?.subscribe(),
+            lag,
+            missed: 0,
+        })
+    }
+
+    fn sender<T: Clone + Send + 'static>(
+        &self,
+        topic: &Topic<T>,
+    ) -> Result<broadcast::Sender<T>, ActorError> {
+        let mut topics = self.topics.lock().unwrap();
+        let sender = topics
+            .entry(topic.name)
+            .or_insert_with(|| Box::new(broadcast::channel::<T>(topic.capacity).0));
+
+        sender
+            .downcast_ref::<broadcast::Sender<T>>()
+            .cloned()
+            .ok_or_else(|| ActorError::TopicMismatch(topic.name.to_string()))
+    }
+}
+
+/// Subscriptions forwarding events to an actor instance, aborted when the
+/// instance stops.
+#[derive(Debug, Clone, Default)]
+pub(super) struct Forwarders {
+    running: Arc<Mutex<Vec<AbortHandle>>>,
+}
+
+impl Forwarders {
+    pub fn forward<A, T, F>(
+        &self,
+        mut subscription: Subscription<T>,
+        mailbox: WeakMailbox<A>,
+        map: F,
+    ) where
+        A: Actor + 'static,
+        T: Clone + Send + 'static,
+        F: Fn(T) -> A::Message + Send + 'static,
+    {
+        self.spawn(async move {
+            while let Some(event) = subscription.recv().await {
+                let Some(mailbox) = mailbox.upgrade() else {
+                    return;
+                };
+
+                if mailbox.send(map(event)).await.is_err() {
+                    return;
+                }
+            }
+        });
+    }
+
+    pub fn spawn<F: Future<Output = ()> + Send + 'static>(&self, forwarder: F) {
+        let handle = tokio::spawn(forwarder);
+        let mut running = self.running.lock().unwrap();
+
+        running.retain(|forwarder| !forwarder.is_finished());
+        running.push(handle.abort_handle());
+    }
+
+    pub fn cancel_all(&self) {
+        for forwarder in self.running.lock().unwrap().drain(..) {
+            forwarder.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use async_trait::async_trait;
+
+    use crate::actor::{Context, Workspace};
+
+    use super::*;
+
+    const TEMPERATURE: Topic<i32> = Topic::with_capacity("temperature", 2);
+
+    #[tokio::test]
+    async fn publish_to_every_subscription() {
+        let bus = EventBus::default();
+        let mut first = bus.subscribe(&TEMPERATURE, Lag::Skip).unwrap();
+        let mut second = bus.subscribe(&TEMPERATURE, Lag::Skip).unwrap();
+
+        assert_eq!(2, bus.publish(&TEMPERATURE, 21).unwrap());
+
+        assert_eq!(Some(21), first.recv().await);
+        assert_eq!(Some(21), second.recv().await);
+    }
+
+    #[tokio::test]
+    async fn publish_without_subscription() {
+        let bus = EventBus::default();
+
+        assert_eq!(0, bus.publish(&TEMPERATURE, 21).unwrap());
+    }
+
+    #[tokio::test]
+    async fn reject_topic_with_another_event_type() {
+        let bus = EventBus::default();
+        let _ = bus.subscribe(&TEMPERATURE, Lag::Skip).unwrap();
+
+        assert!(matches!(
+            bus.publish(&Topic::<String>::new("temperature"), "hot".to_string()),
+            Err(ActorError::TopicMismatch(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn lagging_subscription_skips_missed_events() {
+        let bus = EventBus::default();
+        let mut subscription = bus.subscribe(&TEMPERATURE, Lag::Skip).unwrap();
+
+        for temperature in 0..5 {
+            bus.publish(&TEMPERATURE, temperature).unwrap();
+        }
+
+        assert_eq!(Some(3), subscription.recv().await);
+        assert_eq!(3, subscription.missed());
+        assert_eq!(Some(4), subscription.recv().await);
+    }
+
+    #[tokio::test]
+    async fn lagging_subscription_disconnects() {
+        let bus = EventBus::default();
+        let mut subscription = bus.subscribe(&TEMPERATURE, Lag::Disconnect).unwrap();
+
+        for temperature in 0..5 {
+            bus.publish(&TEMPERATURE, temperature).unwrap();
+        }
+
+        assert_eq!(None, subscription.recv().await);
+    }
+
+    #[derive(Debug)]
+    enum Message {
+        Temperature(i32),
+        Last,
+    }
+
+    #[derive(Debug, Default)]
+    struct ThermometerActor {
+        last: Option<i32>,
+    }
+
+    #[async_trait]
+    impl Actor for ThermometerActor {
+        type Message = Message;
+
+        async fn started(&mut self, ctx: &Context<Self>) -> Result<(), ActorError> {
+            ctx.subscribe(&TEMPERATURE, Lag::Skip, Message::Temperature)
+        }
+
+        async fn handle(
+            &mut self,
+            ctx: &Context<Self>,
+            message: Self::Message,
+        ) -> Result<(), ActorError> {
+            match message {
+                Message::Temperature(temperature) => self.last = Some(temperature),
+                Message::Last => ctx.reply(self.last),
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn actor_receives_events_in_its_mailbox() {
+        let workspace = Workspace::default();
+        let (mailbox, _) = workspace
+            .spawn("thermometer", ThermometerActor::default())
+            .unwrap();
+
+        let last: Option<i32> = mailbox
+            .ask(Message::Last, Duration::from_secs(1))
+            .await
+            .unwrap();
+        assert_eq!(None, last);
+
+        workspace.bus().publish(&TEMPERATURE, 21).unwrap();
+        tokio::task::yield_now().await;
+
+        let last: Option<i32> = mailbox
+            .ask(Message::Last, Duration::from_secs(1))
+            .await
+            .unwrap();
+        assert_eq!(Some(21), last);
+    }
+}
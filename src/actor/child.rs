@@ -0,0 +1,400 @@
+use std::{
+    any::Any,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::task::JoinHandle;
+use tracing::warn;
+use uuid::Uuid;
+
+use super::{
+    registry::Registry,
+    supervisor::{Supervisor, SupervisorEvent},
+};
+
+/// How long `stop_all` waits for each child before aborting it.
+const CHILD_STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug)]
+struct Child {
+    id: Uuid,
+    name: String,
+    handle: JoinHandle<()>,
+    /// Keeps the inbox of the child open as long as its parent runs.
+    mailbox: Box<dyn Any + Send>,
+}
+
+/// Actors spawned by an actor instance, stopped when the instance stops.
+/// They are supervised in a scope of their own, so they are not restarted
+/// along with the siblings of their parent.
+#[derive(Debug, Clone)]
+pub(super) struct Children {
+    running: Arc<Mutex<Vec<Child>>>,
+    supervisor: Supervisor,
+    registry: Registry,
+}
+
+impl Children {
+    pub fn new(supervisor: Supervisor, registry: Registry) -> Self {
+        Children {
+            running: Arc::new(Mutex::new(Vec::new())),
+            supervisor,
+            registry,
+        }
+    }
+
+    pub fn supervisor(&self) -> &Supervisor {
+        &self.supervisor
+    }
+
+    pub fn track<M: Send + 'static>(
+        &self,
+        id: Uuid,
+        name: &str,
+        handle: JoinHandle<()>,
+        mailbox: M,
+    ) {
+        let mut running = self.running.lock().unwrap();
+
+        running.retain(|child| !child.handle.is_finished());
+        running.push(Child {
+            id,
+            name: name.to_string(),
+            handle,
+            mailbox: Box::new(mailbox),
+        });
+    }
+
+    /// Asks every child to drain its inbox and stop, then waits for them. A
+    /// child still running after `CHILD_STOP_TIMEOUT` is aborted.
+    pub async fn stop_all(&self) {
+        let children: Vec<_> = self.running.lock().unwrap().drain(..).collect();
+
+        for child in children.iter() {
+            self.supervisor.stop(child.id);
+        }
+
+        for mut child in children {
+            if tokio::time::timeout(CHILD_STOP_TIMEOUT, &mut child.handle)
+                .await
+                .is_ok()
+            {
+                continue;
+            }
+
+            warn!("child `{}` did not stop in time, aborting it", child.name);
+            child.handle.abort();
+            self.registry.deregister(&child.name, child.id);
+            self.supervisor.deregister(child.id);
+            self.supervisor
+                .emit(SupervisorEvent::Stopped { id: child.id });
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use async_trait::async_trait;
+
+    use crate::actor::{
+        mailbox::Mailbox,
+        supervisor::{Strategy, SupervisorEvent, SupervisorOptions},
+        Actor, ActorError, Context, StopReason, Workspace,
+    };
+
+    use super::*;
+
+    type Journal = Arc<Mutex<Vec<String>>>;
+
+    #[derive(Debug)]
+    enum Device {
+        Quit,
+        Crash,
+        Hang,
+    }
+
+    struct DeviceActor {
+        journal: Journal,
+    }
+
+    #[async_trait]
+    impl Actor for DeviceActor {
+        type Message = Device;
+
+        async fn handle(
+            &mut self,
+            _ctx: &Context<Self>,
+            message: Device,
+        ) -> Result<(), ActorError> {
+            match message {
+                Device::Quit => Err(ActorError::Quit),
+                Device::Crash => Err(ActorError::Handler("crash".to_string())),
+                Device::Hang => std::future::pending().await,
+            }
+        }
+
+        async fn stopped(&mut self, ctx: &Context<Self>, _reason: &StopReason) {
+            self.journal
+                .lock()
+                .unwrap()
+                .push(format!("{} stopped", ctx.name()));
+        }
+    }
+
+    #[derive(Debug)]
+    enum Vm {
+        Quit,
+        Crash,
+        Ping,
+        PingMyself,
+        Pings,
+        Watch(String),
+        Terminated(String),
+    }
+
+    struct VmActor {
+        journal: Journal,
+        pings: usize,
+    }
+
+    #[async_trait]
+    impl Actor for VmActor {
+        type Message = Vm;
+
+        async fn started(&mut self, ctx: &Context<Self>) -> Result<(), ActorError> {
+            for device in ["qmp", "serial"] {
+                let journal = self.journal.clone();
+                ctx.spawn_supervised_child(device, move || DeviceActor {
+                    journal: journal.clone(),
+                })?;
+            }
+            Ok(())
+        }
+
+        async fn handle(&mut self, ctx: &Context<Self>, message: Vm) -> Result<(), ActorError> {
+            match message {
+                Vm::Quit => return Err(ActorError::Quit),
+                Vm::Crash => return Err(ActorError::Handler("crash".to_string())),
+                Vm::Ping => self.pings += 1,
+                Vm::PingMyself => {
+                    let mailbox = ctx.mailbox().ok_or(ActorError::Gone)?;
+                    mailbox.try_send(Vm::Ping)?;
+                }
+                Vm::Pings => ctx.reply(self.pings),
+                Vm::Watch(name) => {
+                    let result = ctx.watch(&name, Vm::Terminated(name.clone()));
+                    ctx.reply(result.map_err(|e| e.to_string()));
+                }
+                Vm::Terminated(name) => {
+                    self.journal
+                        .lock()
+                        .unwrap()
+                        .push(format!("{} terminated", name));
+                }
+            }
+            Ok(())
+        }
+
+        async fn stopped(&mut self, ctx: &Context<Self>, _reason: &StopReason) {
+            self.journal
+                .lock()
+                .unwrap()
+                .push(format!("{} stopped", ctx.name()));
+        }
+    }
+
+    fn vm(journal: &Journal) -> VmActor {
+        VmActor {
+            journal: journal.clone(),
+            pings: 0,
+        }
+    }
+
+    fn sorted(journal: &Journal) -> Vec<String> {
+        let mut entries = journal.lock().unwrap().clone();
+        entries.sort();
+        entries
+    }
+
+    async fn pings(mailbox: &Mailbox<VmActor>) -> usize {
+        mailbox
+            .ask(Vm::Pings, Duration::from_secs(1))
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn register_children_under_their_parent() {
+        let workspace = Workspace::default();
+        let journal = Journal::default();
+        let (mailbox, _) = workspace.spawn("vm", vm(&journal)).unwrap();
+        pings(&mailbox).await;
+
+        let names: Vec<_> = workspace
+            .actors()
+            .into_iter()
+            .map(|actor| actor.name)
+            .collect();
+        assert_eq!(vec!["vm", "vm/qmp", "vm/serial"], names);
+    }
+
+    #[tokio::test]
+    async fn stop_children_before_their_parent() {
+        let workspace = Workspace::default();
+        let journal = Journal::default();
+        let (mailbox, handle) = workspace.spawn("vm", vm(&journal)).unwrap();
+
+        mailbox.send(Vm::Quit).await.unwrap();
+        handle.await.unwrap();
+
+        assert_eq!(
+            Some("vm stopped"),
+            journal.lock().unwrap().last().map(String::as_str)
+        );
+        assert_eq!(
+            vec!["vm stopped", "vm/qmp stopped", "vm/serial stopped"],
+            sorted(&journal)
+        );
+        assert!(workspace.actors().is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn respawn_children_when_parent_restarts() {
+        let workspace = Workspace::default();
+        let mut events = workspace.events();
+        let journal = Journal::default();
+        let (mailbox, _) = workspace
+            .spawn_supervised("vm", {
+                let journal = journal.clone();
+                move || vm(&journal)
+            })
+            .unwrap();
+
+        mailbox.send(Vm::Crash).await.unwrap();
+        while !matches!(
+            events.recv().await.unwrap(),
+            SupervisorEvent::Restarted { .. }
+        ) {}
+        pings(&mailbox).await;
+
+        assert_eq!(3, workspace.actors().len());
+        assert_eq!(
+            vec!["vm stopped", "vm/qmp stopped", "vm/serial stopped"],
+            sorted(&journal)
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn restart_children_apart_from_the_siblings_of_their_parent() {
+        let workspace = Workspace::with_options(
+            "supervised",
+            SupervisorOptions {
+                strategy: Strategy::OneForAll,
+                ..Default::default()
+            },
+        );
+        let mut events = workspace.events();
+        let journal = Journal::default();
+        let (vm_mailbox, _) = workspace
+            .spawn_supervised("vm", {
+                let journal = journal.clone();
+                move || vm(&journal)
+            })
+            .unwrap();
+        let (other, _) = workspace
+            .spawn_supervised("other", {
+                let journal = journal.clone();
+                move || vm(&journal)
+            })
+            .unwrap();
+
+        other.send(Vm::Ping).await.unwrap();
+        pings(&vm_mailbox).await;
+        let qmp: Mailbox<DeviceActor> = workspace.lookup("vm/qmp").unwrap();
+        qmp.send(Device::Crash).await.unwrap();
+        while !matches!(
+            events.recv().await.unwrap(),
+            SupervisorEvent::Restarted { .. }
+        ) {}
+
+        assert_eq!(1, pings(&other).await);
+        assert_eq!(
+            vec!["vm/qmp stopped", "vm/serial stopped"],
+            sorted(&journal)
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn abort_children_that_do_not_stop_in_time() {
+        let workspace = Workspace::default();
+        let journal = Journal::default();
+        let (mailbox, handle) = workspace.spawn("vm", vm(&journal)).unwrap();
+        pings(&mailbox).await;
+
+        let qmp: Mailbox<DeviceActor> = workspace.lookup("vm/qmp").unwrap();
+        qmp.send(Device::Hang).await.unwrap();
+        mailbox.send(Vm::Quit).await.unwrap();
+        handle.await.unwrap();
+
+        assert_eq!(vec!["vm stopped", "vm/serial stopped"], sorted(&journal));
+        assert!(workspace.actors().is_empty());
+    }
+
+    #[tokio::test]
+    async fn send_to_own_mailbox() {
+        let workspace = Workspace::default();
+        let (mailbox, _) = workspace.spawn("vm", vm(&Journal::default())).unwrap();
+
+        mailbox.send(Vm::PingMyself).await.unwrap();
+        pings(&mailbox).await;
+
+        assert_eq!(1, pings(&mailbox).await);
+    }
+
+    #[tokio::test]
+    async fn notify_watcher_when_watched_actor_stops() {
+        let workspace = Workspace::default();
+        let journal = Journal::default();
+        let (watcher, _) = workspace.spawn("watcher", vm(&journal)).unwrap();
+        let (device, handle) = workspace
+            .spawn(
+                "device",
+                DeviceActor {
+                    journal: journal.clone(),
+                },
+            )
+            .unwrap();
+
+        let watched: Result<(), String> = watcher
+            .ask(Vm::Watch("device".to_string()), Duration::from_secs(1))
+            .await
+            .unwrap();
+        assert!(watched.is_ok());
+
+        device.send(Device::Quit).await.unwrap();
+        handle.await.unwrap();
+        tokio::task::yield_now().await;
+        pings(&watcher).await;
+
+        assert!(journal
+            .lock()
+            .unwrap()
+            .contains(&"device terminated".to_string()));
+    }
+
+    #[tokio::test]
+    async fn refuse_to_watch_unknown_actor() {
+        let workspace = Workspace::default();
+        let (watcher, _) = workspace.spawn("watcher", vm(&Journal::default())).unwrap();
+
+        let watched: Result<(), String> = watcher
+            .ask(Vm::Watch("ghost".to_string()), Duration::from_secs(1))
+            .await
+            .unwrap();
+
+        assert_eq!(Err("actor `ghost` not found".to_string()), watched);
+    }
+}
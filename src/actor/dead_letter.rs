@@ -0,0 +1,271 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::{
+    bus::{EventBus, Topic},
+    mailbox::Priority,
+};
+
+/// Number of dead letters kept for `Workspace::dead_letters`.
+const DEAD_LETTERS_HISTORY: usize = 1024;
+
+/// Every dead letter of a workspace is published on this topic.
+pub const DEAD_LETTERS: Topic<DeadLetter> = Topic::new("actor.dead_letters");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum DeadLetterReason {
+    /// The actor stopped accepting messages.
+    Undeliverable,
+    /// The mailbox or the stash was full and its overflow policy discarded
+    /// the message.
+    Dropped,
+    /// The deadline of the message passed while it was queued.
+    Expired,
+}
+
+impl std::fmt::Display for DeadLetterReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeadLetterReason::Undeliverable => write!(f, "undeliverable"),
+            DeadLetterReason::Dropped => write!(f, "dropped"),
+            DeadLetterReason::Expired => write!(f, "expired"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct DeadLetter {
+    pub target: String,
+    pub priority: Priority,
+    pub correlation_id: Uuid,
+    /// `Debug` rendering of the message.
+    pub message: String,
+    pub reason: DeadLetterReason,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Sink collecting the messages that never reached their actor.
+#[derive(Debug, Clone)]
+pub struct DeadLetters {
+    recent: Arc<Mutex<VecDeque<DeadLetter>>>,
+    bus: EventBus,
+}
+
+impl DeadLetters {
+    pub(super) fn new(bus: EventBus) -> Self {
+        DeadLetters {
+            recent: Arc::new(Mutex::new(VecDeque::new())),
+            bus,
+        }
+    }
+
+    pub(super) fn record(&self, letter: DeadLetter) {
+        {
+            let mut recent = self.recent.lock().unwrap();
+            if recent.len() == DEAD_LETTERS_HISTORY {
+                recent.pop_front();
+            }
+            recent.push_back(letter.clone());
+        }
+
+        let _ = self.bus.publish(&DEAD_LETTERS, letter);
+    }
+
+    /// Most recent dead letters, oldest first.
+    pub fn list(&self) -> Vec<DeadLetter> {
+        self.recent.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use async_trait::async_trait;
+
+    use crate::actor::{
+        bus::Lag,
+        mailbox::{make_mailbox_with_options, Envelop, MailboxOptions, Overflow},
+        Actor, ActorError, Context, Workspace,
+    };
+
+    use super::*;
+
+    #[derive(Debug)]
+    enum Message {
+        Start(u32),
+        Quit,
+    }
+
+    #[derive(Debug)]
+    struct VmActor;
+
+    #[async_trait]
+    impl Actor for VmActor {
+        type Message = Message;
+
+        async fn handle(
+            &mut self,
+            _ctx: &Context<Self>,
+            message: Self::Message,
+        ) -> Result<(), ActorError> {
+            match message {
+                Message::Start(_) => Ok(()),
+                Message::Quit => Err(ActorError::Quit),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn record_message_sent_to_stopped_actor() {
+        let workspace = Workspace::default();
+        let (mailbox, handle) = workspace.spawn("vm", VmActor).unwrap();
+
+        mailbox.send(Message::Quit).await.unwrap();
+        handle.await.unwrap();
+
+        assert!(mailbox
+            .send_with_envelop(Envelop::high(Message::Start(7)))
+            .await
+            .is_err());
+
+        let letters = workspace.dead_letters();
+        assert_eq!(1, letters.len());
+        assert_eq!("vm", letters[0].target);
+        assert_eq!(Priority::High, letters[0].priority);
+        assert_eq!("Start(7)", letters[0].message);
+        assert_eq!(DeadLetterReason::Undeliverable, letters[0].reason);
+    }
+
+    #[tokio::test]
+    async fn record_messages_discarded_by_overflow() {
+        let sink = DeadLetters::new(EventBus::default());
+        let (mailbox, _inbox) = make_mailbox_with_options::<VmActor>(MailboxOptions {
+            capacity: 1,
+            overflow: Overflow::DropOldest,
+            ..Default::default()
+        });
+        mailbox.route_dead_letters("vm", sink.clone());
+
+        mailbox.try_send(Message::Start(1)).unwrap();
+        mailbox.try_send(Message::Start(2)).unwrap();
+
+        let letters = sink.list();
+        assert_eq!(1, letters.len());
+        assert_eq!("Start(1)", letters[0].message);
+        assert_eq!(DeadLetterReason::Dropped, letters[0].reason);
+    }
+
+    #[tokio::test]
+    async fn record_messages_discarded_by_every_overflow_policy() {
+        for (overflow, discarded) in [
+            (Overflow::Reject, "Start(2)"),
+            (Overflow::DropNewest, "Start(2)"),
+            (Overflow::DropOldest, "Start(1)"),
+        ] {
+            let sink = DeadLetters::new(EventBus::default());
+            let (mailbox, _inbox) = make_mailbox_with_options::<VmActor>(MailboxOptions {
+                capacity: 1,
+                overflow,
+                ..Default::default()
+            });
+            mailbox.route_dead_letters("vm", sink.clone());
+
+            mailbox
+                .try_send_with_envelop(Envelop::high(Message::Start(1)))
+                .unwrap();
+            let _ = mailbox.try_send_with_envelop(Envelop::high(Message::Start(2)));
+
+            let letters = sink.list();
+            assert_eq!(1, letters.len(), "{:?}", overflow);
+            assert_eq!("vm", letters[0].target);
+            assert_eq!(Priority::High, letters[0].priority);
+            assert_eq!(discarded, letters[0].message, "{:?}", overflow);
+            assert_eq!(DeadLetterReason::Dropped, letters[0].reason);
+        }
+    }
+
+    #[tokio::test]
+    async fn record_messages_left_in_dropped_inbox() {
+        let sink = DeadLetters::new(EventBus::default());
+        let (mailbox, inbox) = make_mailbox_with_options::<VmActor>(MailboxOptions::default());
+        mailbox.route_dead_letters("vm", sink.clone());
+
+        mailbox.try_send(Message::Start(1)).unwrap();
+        mailbox
+            .try_send_with_envelop(Envelop::high(Message::Start(2)))
+            .unwrap();
+        drop(inbox);
+
+        assert_eq!(
+            vec!["Start(2)", "Start(1)"],
+            sink.list()
+                .iter()
+                .map(|letter| letter.message.as_str())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn record_expired_messages() {
+        let sink = DeadLetters::new(EventBus::default());
+        let (mailbox, mut inbox) = make_mailbox_with_options::<VmActor>(MailboxOptions::default());
+        mailbox.route_dead_letters("vm", sink.clone());
+
+        mailbox
+            .try_send_with_envelop(
+                Envelop::normal(Message::Start(1)).with_ttl(Duration::from_secs(600)),
+            )
+            .unwrap();
+        tokio::time::sleep(Duration::from_secs(601)).await;
+
+        assert!(inbox.try_recv_envelop().is_none());
+        let letters = sink.list();
+        assert_eq!("Start(1)", letters[0].message);
+        assert_eq!(DeadLetterReason::Expired, letters[0].reason);
+    }
+
+    #[tokio::test]
+    async fn publish_dead_letters_on_the_bus() {
+        let bus = EventBus::default();
+        let sink = DeadLetters::new(bus.clone());
+        let mut subscription = bus.subscribe(&DEAD_LETTERS, Lag::Skip).unwrap();
+        let (mailbox, inbox) = make_mailbox_with_options::<VmActor>(MailboxOptions::default());
+        mailbox.route_dead_letters("vm", sink);
+        drop(inbox);
+
+        assert!(mailbox.try_send(Message::Start(3)).is_err());
+
+        let letter = subscription.recv().await.unwrap();
+        assert_eq!("vm", letter.target);
+        assert_eq!("Start(3)", letter.message);
+    }
+
+    #[tokio::test]
+    async fn keep_a_bounded_history() {
+        let sink = DeadLetters::new(EventBus::default());
+
+        for n in 0..DEAD_LETTERS_HISTORY + 1 {
+            sink.record(DeadLetter {
+                target: "vm".to_string(),
+                priority: Priority::Normal,
+                correlation_id: Uuid::new_v4(),
+                message: n.to_string(),
+                reason: DeadLetterReason::Undeliverable,
+                created_at: Utc::now(),
+            });
+        }
+
+        let letters = sink.list();
+        assert_eq!(DEAD_LETTERS_HISTORY, letters.len());
+        assert_eq!("1", letters[0].message);
+    }
+}
@@ -0,0 +1,380 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::{fs, io::AsyncWriteExt};
+use tracing::warn;
+
+use super::ActorError;
+
+/// Event appended by a persistent actor, `sequence` starts at 1 and grows by
+/// one with each event of the same persistence id.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub sequence: u64,
+    pub event: Value,
+}
+
+/// State of a persistent actor once every event up to `sequence` is applied.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub sequence: u64,
+    pub state: Value,
+}
+
+/// Storage of the events and snapshots of persistent actors, keyed by
+/// persistence id.
+#[async_trait]
+pub trait Journal: Send + Sync + std::fmt::Debug {
+    /// Returns once `entry` is durable.
+    async fn append(&self, id: &str, entry: JournalEntry) -> Result<(), ActorError>;
+
+    /// Events whose sequence is greater than `after`, oldest first.
+    async fn replay(&self, id: &str, after: u64) -> Result<Vec<JournalEntry>, ActorError>;
+
+    async fn load_snapshot(&self, id: &str) -> Result<Option<Snapshot>, ActorError>;
+
+    /// Replaces the snapshot of `id`, the events it covers may be discarded.
+    async fn save_snapshot(&self, id: &str, snapshot: Snapshot) -> Result<(), ActorError>;
+}
+
+#[derive(Debug, Default)]
+struct Stored {
+    events: Vec<JournalEntry>,
+    snapshot: Option<Snapshot>,
+}
+
+/// Journal kept in memory, shared by its clones.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryJournal {
+    stored: Arc<Mutex<HashMap<String, Stored>>>,
+}
+
+#[async_trait]
+impl Journal for MemoryJournal {
+    async fn append(&self, id: &str, entry: JournalEntry) -> Result<(), ActorError> {
+        let mut stored = self.stored.lock().unwrap();
+        stored.entry(id.to_string()).or_default().events.push(entry);
+        Ok(())
+    }
+
+    async fn replay(&self, id: &str, after: u64) -> Result<Vec<JournalEntry>, ActorError> {
+        let stored = self.stored.lock().unwrap();
+        Ok(stored
+            .get(id)
+            .map(|stored| {
+                stored
+                    .events
+                    .iter()
+                    .filter(|entry| entry.sequence > after)
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    async fn load_snapshot(&self, id: &str) -> Result<Option<Snapshot>, ActorError> {
+        let stored = self.stored.lock().unwrap();
+        Ok(stored.get(id).and_then(|stored| stored.snapshot.clone()))
+    }
+
+    async fn save_snapshot(&self, id: &str, snapshot: Snapshot) -> Result<(), ActorError> {
+        let mut stored = self.stored.lock().unwrap();
+        let stored = stored.entry(id.to_string()).or_default();
+        stored
+            .events
+            .retain(|entry| entry.sequence > snapshot.sequence);
+        stored.snapshot = Some(snapshot);
+        Ok(())
+    }
+}
+
+/// Journal writing one JSON line per event in `<id>.events` and the latest
+/// snapshot in `<id>.snapshot`, both under `directory`.
+///
+/// Events are synced to disk before `append` returns, a failed append is
+/// truncated away. A line torn by a crash is discarded on the next replay.
+#[derive(Debug, Clone)]
+pub struct FileJournal {
+    directory: PathBuf,
+}
+
+impl FileJournal {
+    /// Creates `directory` when it does not exist.
+    pub async fn open<P: Into<PathBuf>>(directory: P) -> Result<Self, ActorError> {
+        let directory = directory.into();
+        fs::create_dir_all(&directory)
+            .await
+            .map_err(|e| journal_error(&directory, e))?;
+
+        Ok(FileJournal { directory })
+    }
+
+    fn events_path(&self, id: &str) -> PathBuf {
+        self.directory.join(format!("{}.events", file_name(id)))
+    }
+
+    fn snapshot_path(&self, id: &str) -> PathBuf {
+        self.directory.join(format!("{}.snapshot", file_name(id)))
+    }
+
+    async fn read_events(&self, id: &str) -> Result<Vec<JournalEntry>, ActorError> {
+        let path = self.events_path(id);
+        let Some(content) = read(&path).await? else {
+            return Ok(Vec::new());
+        };
+
+        // everything after the last newline was cut short by a crash.
+        let complete = content.rfind('\n').map_or(0, |end| end + 1);
+        if complete < content.len() {
+            warn!("discarding torn event at the end of `{}`", path.display());
+            let file = fs::OpenOptions::new()
+                .write(true)
+                .open(&path)
+                .await
+                .map_err(|e| journal_error(&path, e))?;
+            file.set_len(complete as u64)
+                .await
+                .map_err(|e| journal_error(&path, e))?;
+        }
+
+        content[..complete]
+            .lines()
+            .map(|line| serde_json::from_str(line).map_err(|e| journal_error(&path, e)))
+            .collect()
+    }
+}
+
+#[async_trait]
+impl Journal for FileJournal {
+    async fn append(&self, id: &str, entry: JournalEntry) -> Result<(), ActorError> {
+        let path = self.events_path(id);
+        let mut line = serde_json::to_vec(&entry).map_err(|e| journal_error(&path, e))?;
+        line.push(b'\n');
+
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .await
+            .map_err(|e| journal_error(&path, e))?;
+        let len = file
+            .metadata()
+            .await
+            .map_err(|e| journal_error(&path, e))?
+            .len();
+
+        let written = match file.write_all(&line).await {
+            Ok(()) => file.sync_data().await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = written {
+            // a partial line would be glued to the next event, and a line not
+            // synced must not be replayed since the caller sees the failure.
+            if let Err(e) = file.set_len(len).await {
+                warn!(
+                    "can't truncate `{}` after a failed append: {}",
+                    path.display(),
+                    e
+                );
+            }
+            return Err(journal_error(&path, e));
+        }
+
+        Ok(())
+    }
+
+    async fn replay(&self, id: &str, after: u64) -> Result<Vec<JournalEntry>, ActorError> {
+        let mut events = self.read_events(id).await?;
+        events.retain(|entry| entry.sequence > after);
+        Ok(events)
+    }
+
+    async fn load_snapshot(&self, id: &str) -> Result<Option<Snapshot>, ActorError> {
+        let path = self.snapshot_path(id);
+
+        read(&path)
+            .await?
+            .map(|content| serde_json::from_str(&content).map_err(|e| journal_error(&path, e)))
+            .transpose()
+    }
+
+    async fn save_snapshot(&self, id: &str, snapshot: Snapshot) -> Result<(), ActorError> {
+        let path = self.snapshot_path(id);
+        let content = serde_json::to_vec(&snapshot).map_err(|e| journal_error(&path, e))?;
+        replace(&path, &content).await?;
+
+        let mut remaining = Vec::new();
+        for entry in self.replay(id, snapshot.sequence).await? {
+            let path = self.events_path(id);
+            serde_json::to_writer(&mut remaining, &entry).map_err(|e| journal_error(&path, e))?;
+            remaining.push(b'\n');
+        }
+        replace(&self.events_path(id), &remaining).await
+    }
+}
+
+/// Percent-encodes the bytes of `id` that are not safe in a file name, like
+/// the `/` of child actors.
+fn file_name(id: &str) -> String {
+    id.bytes()
+        .map(|byte| match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'_' | b'.' => {
+                (byte as char).to_string()
+            }
+            byte => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+async fn read(path: &Path) -> Result<Option<String>, ActorError> {
+    match fs::read_to_string(path).await {
+        Ok(content) => Ok(Some(content)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(journal_error(path, e)),
+    }
+}
+
+/// Writes `content` next to `path` and renames it over `path`, readers never
+/// see a partial file.
+async fn replace(path: &Path, content: &[u8]) -> Result<(), ActorError> {
+    let mut staging = path.as_os_str().to_owned();
+    staging.push(".tmp");
+    let staging = PathBuf::from(staging);
+
+    let mut file = fs::File::create(&staging)
+        .await
+        .map_err(|e| journal_error(&staging, e))?;
+    file.write_all(content)
+        .await
+        .map_err(|e| journal_error(&staging, e))?;
+    file.sync_data()
+        .await
+        .map_err(|e| journal_error(&staging, e))?;
+    fs::rename(&staging, path)
+        .await
+        .map_err(|e| journal_error(path, e))
+}
+
+fn journal_error<E: std::fmt::Display>(path: &Path, error: E) -> ActorError {
+    ActorError::Journal(format!("{}: {}", path.display(), error))
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use crate::actor::testkit::Scratch;
+
+    use super::*;
+
+    fn entry(sequence: u64) -> JournalEntry {
+        JournalEntry {
+            sequence,
+            event: json!({ "disk": sequence }),
+        }
+    }
+
+    #[tokio::test]
+    async fn replay_events_appended_by_another_instance() {
+        let scratch = Scratch::new("journal");
+        let journal = FileJournal::open(&scratch.path()).await.unwrap();
+        for sequence in 1..=3 {
+            journal.append("vm", entry(sequence)).await.unwrap();
+        }
+
+        let reopened = FileJournal::open(&scratch.path()).await.unwrap();
+
+        assert_eq!(
+            vec![entry(2), entry(3)],
+            reopened.replay("vm", 1).await.unwrap()
+        );
+        assert!(reopened.replay("other", 0).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn discard_events_covered_by_snapshot() {
+        let scratch = Scratch::new("journal");
+        let journal = FileJournal::open(&scratch.path()).await.unwrap();
+        for sequence in 1..=3 {
+            journal.append("vm/qmp", entry(sequence)).await.unwrap();
+        }
+
+        let snapshot = Snapshot {
+            sequence: 2,
+            state: json!([1, 2]),
+        };
+        journal
+            .save_snapshot("vm/qmp", snapshot.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            Some(snapshot),
+            journal.load_snapshot("vm/qmp").await.unwrap()
+        );
+        assert_eq!(vec![entry(3)], journal.replay("vm/qmp", 0).await.unwrap());
+        assert!(scratch.path().join("vm%2Fqmp.events").exists());
+    }
+
+    #[tokio::test]
+    async fn truncate_event_torn_by_a_crash() {
+        let scratch = Scratch::new("journal");
+        let journal = FileJournal::open(&scratch.path()).await.unwrap();
+        journal.append("vm", entry(1)).await.unwrap();
+
+        let mut file = fs::OpenOptions::new()
+            .append(true)
+            .open(scratch.path().join("vm.events"))
+            .await
+            .unwrap();
+        file.write_all(b"{\"sequence\":2,\"ev").await.unwrap();
+
+        assert_eq!(vec![entry(1)], journal.replay("vm", 0).await.unwrap());
+
+        journal.append("vm", entry(2)).await.unwrap();
+        assert_eq!(
+            vec![entry(1), entry(2)],
+            journal.replay("vm", 0).await.unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn fail_on_corrupted_event() {
+        let scratch = Scratch::new("journal");
+        let journal = FileJournal::open(&scratch.path()).await.unwrap();
+        fs::write(scratch.path().join("vm.events"), "not json\n")
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            journal.replay("vm", 0).await,
+            Err(ActorError::Journal(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn memory_journal_discards_events_covered_by_snapshot() {
+        let journal = MemoryJournal::default();
+        journal.append("vm", entry(1)).await.unwrap();
+        journal.append("vm", entry(2)).await.unwrap();
+
+        journal
+            .save_snapshot(
+                "vm",
+                Snapshot {
+                    sequence: 1,
+                    state: json!([1]),
+                },
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(vec![entry(2)], journal.replay("vm", 0).await.unwrap());
+    }
+}
@@ -1,19 +1,117 @@
-use tokio::sync::mpsc::{self, Receiver, Sender};
+use std::{
+    any::Any,
+    collections::VecDeque,
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, OnceLock,
+    },
+    time::Duration,
+};
 
-use super::{Actor, ActorError};
+use chrono::Utc;
+use serde::Serialize;
+use tokio::{
+    sync::{oneshot, Notify},
+    time::Instant,
+};
+use tracing::{Instrument, Span};
+use utoipa::ToSchema;
+use uuid::Uuid;
 
+use super::{
+    dead_letter::{DeadLetter, DeadLetterReason, DeadLetters},
+    metrics::QueueDepth,
+    Actor, ActorError,
+};
+
+pub(super) type Reply = Result<Box<dyn Any + Send>, ActorError>;
+
+tokio::task_local! {
+    static CORRELATION_ID: Uuid;
+}
+
+/// Correlation id of the message handled by the current task, `None` outside
+/// of `Actor::handle`.
+pub fn current_correlation_id() -> Option<Uuid> {
+    CORRELATION_ID.try_with(|id| *id).ok()
+}
+
+pub(super) async fn with_correlation_id<F: Future>(id: Uuid, future: F) -> F::Output {
+    CORRELATION_ID.scope(id, future).await
+}
+
+/// Runs `future` with the correlation id and tracing span of the caller, for
+/// tasks sending messages on behalf of a handler.
+pub(super) fn propagate<F: Future>(future: F) -> impl Future<Output = F::Output> {
+    let correlation_id = current_correlation_id();
+    let future = future.instrument(Span::current());
+
+    async move {
+        match correlation_id {
+            Some(id) => with_correlation_id(id, future).await,
+            None => future.await,
+        }
+    }
+}
+
+/// Channel used to answer a message sent with `Mailbox::ask`.
 #[derive(Debug)]
+pub struct ReplyTo(oneshot::Sender<Reply>);
+
+impl ReplyTo {
+    pub(super) fn send(self, reply: Reply) {
+        let _ = self.0.send(reply);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 #[repr(C)]
-enum Priority {
+pub enum Priority {
+    Low = 0,
     Normal = 1,
     High = 5,
+    Critical = 10,
+}
+
+/// Every priority, highest first.
+const PRIORITIES: [Priority; 4] = [
+    Priority::Critical,
+    Priority::High,
+    Priority::Normal,
+    Priority::Low,
+];
+
+impl Priority {
+    /// Index of the priority in `PRIORITIES`.
+    fn level(self) -> usize {
+        match self {
+            Priority::Critical => 0,
+            Priority::High => 1,
+            Priority::Normal => 2,
+            Priority::Low => 3,
+        }
+    }
+
+    /// Messages handled per round of `Fairness::Weighted`.
+    fn weight(self) -> usize {
+        match self {
+            Priority::Critical => 8,
+            Priority::High => 4,
+            Priority::Normal => 2,
+            Priority::Low => 1,
+        }
+    }
 }
 
 impl std::fmt::Display for Priority {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
+            Priority::Low => write!(f, "Low"),
             Priority::Normal => write!(f, "Normal"),
             Priority::High => write!(f, "High"),
+            Priority::Critical => write!(f, "Critical"),
         }
     }
 }
@@ -22,43 +120,494 @@ impl std::fmt::Display for Priority {
 pub struct Envelop<M: std::fmt::Debug> {
     priority: Priority,
     message: M,
+    reply_to: Option<ReplyTo>,
+    deadline: Option<Instant>,
+    correlation_id: Uuid,
+    /// Span of the sender, parent of the span `handle` runs in.
+    span: Span,
 }
 
 impl<M: std::fmt::Debug> Envelop<M> {
-    fn normal(message: M) -> Envelop<M> {
+    pub fn normal(message: M) -> Envelop<M> {
+        Self::with_priority(message, Priority::Normal)
+    }
+
+    pub fn high(message: M) -> Envelop<M> {
+        Self::with_priority(message, Priority::High)
+    }
+
+    /// The envelop keeps the correlation id of the message being handled,
+    /// if any, and the current tracing span.
+    pub fn with_priority(message: M, priority: Priority) -> Envelop<M> {
         Envelop {
             message,
-            priority: Priority::Normal,
+            priority,
+            reply_to: None,
+            deadline: None,
+            correlation_id: current_correlation_id().unwrap_or_else(Uuid::new_v4),
+            span: Span::current(),
         }
     }
 
-    fn high(message: M) -> Envelop<M> {
+    pub fn with_correlation_id(mut self, correlation_id: Uuid) -> Envelop<M> {
+        self.correlation_id = correlation_id;
+        self
+    }
+
+    /// The message is discarded instead of handled once `deadline` passed.
+    pub fn with_deadline(mut self, deadline: Instant) -> Envelop<M> {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Same as `with_deadline`, counting `ttl` from now.
+    pub fn with_ttl(self, ttl: Duration) -> Envelop<M> {
+        self.with_deadline(Instant::now() + ttl)
+    }
+
+    pub fn priority(&self) -> Priority {
+        self.priority
+    }
+
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    pub fn correlation_id(&self) -> Uuid {
+        self.correlation_id
+    }
+
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.deadline
+            .is_some_and(|deadline| deadline <= Instant::now())
+    }
+
+    pub fn message(&self) -> &M {
+        &self.message
+    }
+
+    pub(super) fn with_reply_to(mut self, reply_to: ReplyTo) -> Envelop<M> {
+        self.reply_to = Some(reply_to);
+        self
+    }
+
+    pub(super) fn into_parts(self) -> (M, Option<ReplyTo>) {
+        (self.message, self.reply_to)
+    }
+
+    pub(super) fn take_reply_to(&mut self) -> Option<ReplyTo> {
+        self.reply_to.take()
+    }
+
+    /// Separates the message from the rest of the envelop.
+    pub(super) fn open(self) -> (M, Envelop<()>) {
+        let Envelop {
+            priority,
+            message,
+            reply_to,
+            deadline,
+            correlation_id,
+            span,
+        } = self;
+
+        (
+            message,
+            Envelop {
+                priority,
+                message: (),
+                reply_to,
+                deadline,
+                correlation_id,
+                span,
+            },
+        )
+    }
+}
+
+impl Envelop<()> {
+    /// Puts `message` back in an envelop opened with `open`.
+    pub(super) fn seal<M: std::fmt::Debug>(self, message: M) -> Envelop<M> {
         Envelop {
+            priority: self.priority,
             message,
-            priority: Priority::High,
+            reply_to: self.reply_to,
+            deadline: self.deadline,
+            correlation_id: self.correlation_id,
+            span: self.span,
+        }
+    }
+}
+
+const DEFAULT_MAILBOX_CAPACITY: usize = 32;
+
+/// What a mailbox does with a message sent while the queue for its priority
+/// is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Overflow {
+    /// Wait until the actor makes room, `try_send` fails with `MailboxFull`.
+    #[default]
+    Block,
+    /// Fail with `ActorError::MailboxFull`, the message is recorded as a dead
+    /// letter.
+    Reject,
+    /// Discard the oldest queued message to make room.
+    DropOldest,
+    /// Discard the message being sent.
+    DropNewest,
+}
+
+/// Order in which an inbox serves the queues of each priority.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Fairness {
+    /// Weighted round-robin, each round serves up to 8 critical, 4 high, 2
+    /// normal and 1 low message. A message first in its queue is handled
+    /// within two rounds whatever the load of the other priorities.
+    #[default]
+    Weighted,
+    /// Always serve the highest priority first, lower ones can starve.
+    Strict,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MailboxOptions {
+    /// Number of messages queued per priority.
+    pub capacity: usize,
+    pub overflow: Overflow,
+    pub fairness: Fairness,
+}
+
+impl Default for MailboxOptions {
+    fn default() -> Self {
+        MailboxOptions {
+            capacity: DEFAULT_MAILBOX_CAPACITY,
+            overflow: Overflow::default(),
+            fairness: Fairness::default(),
         }
     }
 }
 
 pub fn make_mailbox<A: Actor>() -> (Mailbox<A>, Inbox<A>) {
-    let (normal_tx, normal_rx) = mpsc::channel::<Envelop<A::Message>>(1);
-    let (high_tx, high_rx) = mpsc::channel::<Envelop<A::Message>>(1);
+    make_mailbox_with_options(A::mailbox_options())
+}
 
-    (
-        Mailbox::new(normal_tx, high_tx),
-        Inbox::new(normal_rx, high_rx),
-    )
+pub fn make_mailbox_with_options<A: Actor>(options: MailboxOptions) -> (Mailbox<A>, Inbox<A>) {
+    let shared = Arc::new(Shared::new(options));
+
+    (Mailbox::new(shared.clone()), Inbox::new(shared))
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
+struct Queues<M: std::fmt::Debug> {
+    /// One queue per priority, indexed by `Priority::level`.
+    by_priority: [VecDeque<Envelop<M>>; PRIORITIES.len()],
+    /// Messages each priority can still be served in the current weighted
+    /// round.
+    credits: [usize; PRIORITIES.len()],
+    /// Stashed messages given back to the actor, served before any other.
+    replay: VecDeque<Envelop<M>>,
+    senders: usize,
+    closed: bool,
+}
+
+impl<M: std::fmt::Debug> Queues<M> {
+    fn new() -> Self {
+        Queues {
+            by_priority: std::array::from_fn(|_| VecDeque::new()),
+            credits: PRIORITIES.map(Priority::weight),
+            replay: VecDeque::new(),
+            senders: 0,
+            closed: false,
+        }
+    }
+
+    fn queue_mut(&mut self, priority: &Priority) -> &mut VecDeque<Envelop<M>> {
+        &mut self.by_priority[priority.level()]
+    }
+
+    fn next(&mut self, fairness: Fairness) -> Option<Envelop<M>> {
+        if let Some(envelop) = self.replay.pop_front() {
+            return Some(envelop);
+        }
+
+        match fairness {
+            Fairness::Strict => self.by_priority.iter_mut().find_map(VecDeque::pop_front),
+            Fairness::Weighted => self.next_weighted(),
+        }
+    }
+
+    fn next_weighted(&mut self) -> Option<Envelop<M>> {
+        for _ in 0..2 {
+            let level = (0..PRIORITIES.len())
+                .find(|level| self.credits[*level] > 0 && !self.by_priority[*level].is_empty());
+
+            if let Some(level) = level {
+                self.credits[level] -= 1;
+                return self.by_priority[level].pop_front();
+            }
+
+            // every priority with queued messages spent its credits.
+            self.credits = PRIORITIES.map(Priority::weight);
+        }
+
+        None
+    }
+
+    fn depth(&self) -> QueueDepth {
+        let len = |priority: Priority| {
+            let replayed = self
+                .replay
+                .iter()
+                .filter(|envelop| envelop.priority == priority)
+                .count();
+
+            self.by_priority[priority.level()].len() + replayed
+        };
+
+        QueueDepth {
+            low: len(Priority::Low),
+            normal: len(Priority::Normal),
+            high: len(Priority::High),
+            critical: len(Priority::Critical),
+        }
+    }
+}
+
+enum Push<M: std::fmt::Debug> {
+    Full(Envelop<M>),
+    Rejected,
+    Expired,
+    Closed,
+}
+
+/// Where the messages that never reach the actor are recorded.
+#[derive(Debug)]
+struct DeadLetterRoute {
+    target: String,
+    sink: DeadLetters,
+}
+
+#[derive(Debug)]
+struct Shared<M: std::fmt::Debug> {
+    options: MailboxOptions,
+    queues: Mutex<Queues<M>>,
+    readable: Notify,
+    writable: Notify,
+    dead_letters: OnceLock<DeadLetterRoute>,
+    expired: AtomicU64,
+}
+
+impl<M: std::fmt::Debug> Shared<M> {
+    fn new(options: MailboxOptions) -> Self {
+        Shared {
+            options,
+            queues: Mutex::new(Queues::new()),
+            readable: Notify::new(),
+            writable: Notify::new(),
+            dead_letters: OnceLock::new(),
+            expired: AtomicU64::new(0),
+        }
+    }
+
+    fn push(&self, envelop: Envelop<M>) -> Result<(), Push<M>> {
+        let mut queues = self.queues.lock().unwrap();
+
+        if queues.closed {
+            drop(queues);
+            self.dead_letter(envelop, DeadLetterReason::Undeliverable);
+            return Err(Push::Closed);
+        }
+
+        if envelop.is_expired() {
+            return Err(Push::Expired);
+        }
+
+        let capacity = self.options.capacity.max(1);
+        let queue = queues.queue_mut(&envelop.priority);
+        let mut dropped = None;
+
+        if queue.len() >= capacity {
+            match self.options.overflow {
+                Overflow::Block => return Err(Push::Full(envelop)),
+                Overflow::Reject => {
+                    drop(queues);
+                    self.dead_letter(envelop, DeadLetterReason::Dropped);
+                    return Err(Push::Rejected);
+                }
+                Overflow::DropNewest => dropped = Some(envelop),
+                Overflow::DropOldest => {
+                    dropped = queue.pop_front();
+                    queue.push_back(envelop);
+                }
+            }
+        } else {
+            queue.push_back(envelop);
+        }
+
+        drop(queues);
+        self.readable.notify_one();
+
+        if let Some(envelop) = dropped {
+            self.dead_letter(envelop, DeadLetterReason::Dropped);
+        }
+
+        Ok(())
+    }
+
+    fn dead_letter(&self, envelop: Envelop<M>, reason: DeadLetterReason) {
+        if let Some(route) = self.dead_letters.get() {
+            route.sink.record(DeadLetter {
+                target: route.target.clone(),
+                priority: envelop.priority,
+                correlation_id: envelop.correlation_id,
+                message: format!("{:?}", envelop.message),
+                reason,
+                created_at: Utc::now(),
+            });
+        }
+    }
+
+    /// Returns the next envelop still in time, the expired ones met on the
+    /// way are discarded.
+    fn pop(&self) -> Option<Option<Envelop<M>>> {
+        let mut expired = Vec::new();
+        let next = {
+            let mut queues = self.queues.lock().unwrap();
+
+            loop {
+                match queues.next(self.options.fairness) {
+                    Some(envelop) if envelop.is_expired() => expired.push(envelop),
+                    Some(envelop) => break Some(Some(envelop)),
+                    None if queues.senders == 0 || queues.closed => break Some(None),
+                    None => break None,
+                }
+            }
+        };
+
+        if !expired.is_empty() || matches!(next, Some(Some(_))) {
+            self.writable.notify_waiters();
+        }
+
+        for envelop in expired {
+            self.expire(envelop);
+        }
+
+        next
+    }
+
+    fn expire(&self, mut envelop: Envelop<M>) {
+        self.expired.fetch_add(1, Ordering::Relaxed);
+
+        if let Some(reply_to) = envelop.reply_to.take() {
+            reply_to.send(Err(ActorError::Expired));
+        }
+
+        self.dead_letter(envelop, DeadLetterReason::Expired);
+    }
+
+    /// Queues `envelops` ahead of every other message, even once the inbox
+    /// is closed.
+    fn replay(&self, envelops: Vec<Envelop<M>>) {
+        self.queues.lock().unwrap().replay.extend(envelops);
+        self.readable.notify_one();
+    }
+
+    fn close(&self) {
+        self.queues.lock().unwrap().closed = true;
+        self.writable.notify_waiters();
+    }
+
+    /// Closes the queues and turns the messages still queued into dead
+    /// letters.
+    fn discard(&self) {
+        let undelivered: Vec<_> = {
+            let mut queues = self.queues.lock().unwrap();
+            queues.closed = true;
+
+            let Queues {
+                by_priority,
+                replay,
+                ..
+            } = &mut *queues;
+
+            replay
+                .drain(..)
+                .chain(by_priority.iter_mut().flat_map(|queue| queue.drain(..)))
+                .collect()
+        };
+        self.writable.notify_waiters();
+
+        for envelop in undelivered {
+            self.dead_letter(envelop, DeadLetterReason::Undeliverable);
+        }
+    }
+}
+
+/// Sending side of an actor's inbox. The inbox stays open while a mailbox
+/// exists, the registry only keeps a weak one.
+#[must_use = "the actor stops once every mailbox is dropped"]
 pub struct Mailbox<A: Actor> {
-    normal_tx: Sender<Envelop<A::Message>>,
-    high_tx: Sender<Envelop<A::Message>>,
+    shared: Arc<Shared<A::Message>>,
+}
+
+impl<A: Actor> std::fmt::Debug for Mailbox<A> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Mailbox")
+            .field("options", &self.shared.options)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<A: Actor> Clone for Mailbox<A> {
+    fn clone(&self) -> Self {
+        Self::new(self.shared.clone())
+    }
+}
+
+impl<A: Actor> Drop for Mailbox<A> {
+    fn drop(&mut self) {
+        let mut queues = self.shared.queues.lock().unwrap();
+        queues.senders -= 1;
+
+        if queues.senders == 0 {
+            self.shared.readable.notify_one();
+        }
+    }
 }
 
 impl<A: Actor> Mailbox<A> {
-    fn new(normal_tx: Sender<Envelop<A::Message>>, high_tx: Sender<Envelop<A::Message>>) -> Self {
-        Self { normal_tx, high_tx }
+    fn new(shared: Arc<Shared<A::Message>>) -> Self {
+        shared.queues.lock().unwrap().senders += 1;
+        Self { shared }
+    }
+
+    pub fn depth(&self) -> QueueDepth {
+        self.shared.queues.lock().unwrap().depth()
+    }
+
+    /// Records the messages that never reach the actor in `sink`, under the
+    /// name `target`. Only the first route set on a mailbox is kept.
+    pub(super) fn route_dead_letters(&self, target: &str, sink: DeadLetters) {
+        let _ = self.shared.dead_letters.set(DeadLetterRoute {
+            target: target.to_string(),
+            sink,
+        });
+    }
+
+    /// Number of messages discarded because their deadline passed while they
+    /// were queued.
+    pub fn expired(&self) -> u64 {
+        self.shared.expired.load(Ordering::Relaxed)
+    }
+
+    /// Returns a handle that does not keep the inbox open.
+    pub fn downgrade(&self) -> WeakMailbox<A> {
+        WeakMailbox {
+            shared: self.shared.clone(),
+        }
     }
 
     pub async fn send(&self, message: A::Message) -> Result<(), ActorError> {
@@ -69,37 +618,186 @@ impl<A: Actor> Mailbox<A> {
         self.routing(message).await
     }
 
-    async fn routing(&self, message: Envelop<A::Message>) -> Result<(), ActorError> {
-        match message.priority {
-            Priority::Normal => self.normal_tx.send(message).await?,
-            Priority::High => self.high_tx.send(message).await?,
+    /// Sends a message without waiting for room in the mailbox.
+    pub fn try_send(&self, message: A::Message) -> Result<(), ActorError> {
+        self.try_send_with_envelop(Envelop::normal(message))
+    }
+
+    pub fn try_send_with_envelop(&self, message: Envelop<A::Message>) -> Result<(), ActorError> {
+        match self.shared.push(message) {
+            Ok(()) => Ok(()),
+            Err(Push::Full(_)) | Err(Push::Rejected) => Err(ActorError::MailboxFull),
+            Err(Push::Expired) => Err(ActorError::Expired),
+            Err(Push::Closed) => Err(closed()),
         }
+    }
 
-        Ok(())
+    /// Sends a message and waits for the handler to answer it with
+    /// `Context::reply`.
+    ///
+    /// The reply type is only checked at runtime: asking for an `R` other
+    /// than the type given to `Context::reply` fails with
+    /// `ActorError::UnexpectedReply`. Callers asking the same message from
+    /// several places should name its reply type once, next to the message.
+    pub async fn ask<R: Send + 'static>(
+        &self,
+        message: A::Message,
+        timeout: Duration,
+    ) -> Result<R, ActorError> {
+        self.ask_with_envelop(Envelop::normal(message), timeout)
+            .await
+    }
+
+    /// Same as `ask`, fails with `ActorError::Expired` when the envelop
+    /// deadline passes before the message is handled.
+    pub async fn ask_with_envelop<R: Send + 'static>(
+        &self,
+        envelop: Envelop<A::Message>,
+        timeout: Duration,
+    ) -> Result<R, ActorError> {
+        let (tx, rx) = oneshot::channel();
+        let envelop = envelop.with_reply_to(ReplyTo(tx));
+
+        let reply = tokio::time::timeout(timeout, async {
+            self.routing(envelop).await.map_err(|e| match e {
+                ActorError::Send(_) => ActorError::Gone,
+                e => e,
+            })?;
+            rx.await.map_err(|_| ActorError::Gone)?
+        })
+        .await
+        .map_err(|_| ActorError::Timeout)??;
+
+        reply
+            .downcast::<R>()
+            .map(|reply| *reply)
+            .map_err(|_| ActorError::UnexpectedReply)
+    }
+
+    async fn routing(&self, mut message: Envelop<A::Message>) -> Result<(), ActorError> {
+        loop {
+            let writable = self.shared.writable.notified();
+            tokio::pin!(writable);
+            writable.as_mut().enable();
+
+            let deadline = message.deadline;
+            match self.shared.push(message) {
+                Ok(()) => return Ok(()),
+                Err(Push::Full(envelop)) => message = envelop,
+                Err(Push::Rejected) => return Err(ActorError::MailboxFull),
+                Err(Push::Expired) => return Err(ActorError::Expired),
+                Err(Push::Closed) => return Err(closed()),
+            }
+
+            match deadline {
+                Some(deadline) => {
+                    if tokio::time::timeout_at(deadline, writable).await.is_err() {
+                        return Err(ActorError::Expired);
+                    }
+                }
+                None => writable.await,
+            }
+        }
     }
 }
 
+pub struct WeakMailbox<A: Actor> {
+    shared: Arc<Shared<A::Message>>,
+}
+
+impl<A: Actor> std::fmt::Debug for WeakMailbox<A> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WeakMailbox")
+            .field("options", &self.shared.options)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<A: Actor> Clone for WeakMailbox<A> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<A: Actor> WeakMailbox<A> {
+    /// Returns a mailbox while the inbox still accepts messages.
+    pub fn upgrade(&self) -> Option<Mailbox<A>> {
+        let mut queues = self.shared.queues.lock().unwrap();
+
+        if queues.senders == 0 || queues.closed {
+            return None;
+        }
+
+        queues.senders += 1;
+        Some(Mailbox {
+            shared: self.shared.clone(),
+        })
+    }
+
+    pub fn depth(&self) -> QueueDepth {
+        self.shared.queues.lock().unwrap().depth()
+    }
+
+    pub fn expired(&self) -> u64 {
+        self.shared.expired.load(Ordering::Relaxed)
+    }
+
+    pub(super) fn replay(&self, envelops: Vec<Envelop<A::Message>>) {
+        self.shared.replay(envelops);
+    }
+
+    pub(super) fn dead_letter(&self, envelop: Envelop<A::Message>, reason: DeadLetterReason) {
+        self.shared.dead_letter(envelop, reason);
+    }
+}
+
+fn closed() -> ActorError {
+    ActorError::Send("mailbox closed".to_string())
+}
+
 pub struct Inbox<A: Actor> {
-    normal_rx: Receiver<Envelop<A::Message>>,
-    high_rx: Receiver<Envelop<A::Message>>,
+    shared: Arc<Shared<A::Message>>,
+}
+
+impl<A: Actor> Drop for Inbox<A> {
+    fn drop(&mut self) {
+        self.shared.discard();
+    }
 }
 
 impl<A: Actor> Inbox<A> {
-    fn new(
-        normal_rx: Receiver<Envelop<A::Message>>,
-        high_rx: Receiver<Envelop<A::Message>>,
-    ) -> Self {
-        Self { normal_rx, high_rx }
+    fn new(shared: Arc<Shared<A::Message>>) -> Self {
+        Self { shared }
     }
 
     pub async fn recv(&mut self) -> Option<A::Message> {
-        let message = tokio::select! {
-            biased;
-             Some(val) = self.high_rx.recv() => val.message,
-             Some(val) = self.normal_rx.recv() => val.message,
-        };
+        self.recv_envelop().await.map(|envelop| envelop.message)
+    }
+
+    /// Stops accepting messages, the ones already queued can still be
+    /// received.
+    pub fn close(&mut self) {
+        self.shared.close();
+    }
+
+    /// Returns the next queued envelop without waiting.
+    pub fn try_recv_envelop(&mut self) -> Option<Envelop<A::Message>> {
+        self.shared.pop().flatten()
+    }
+
+    /// Returns the next envelop in the order set by the mailbox `Fairness`,
+    /// or `None` once every mailbox was dropped or the inbox closed, and the
+    /// queues are empty.
+    pub async fn recv_envelop(&mut self) -> Option<Envelop<A::Message>> {
+        loop {
+            if let Some(envelop) = self.shared.pop() {
+                return envelop;
+            }
 
-        Some(message)
+            self.shared.readable.notified().await;
+        }
     }
 }
 
@@ -120,7 +818,7 @@ mod test {
 
         async fn handle(
             &mut self,
-            _ctx: &Context,
+            _ctx: &Context<Self>,
             message: Self::Message,
         ) -> Result<(), ActorError> {
             match message {
@@ -139,11 +837,7 @@ mod test {
 
     #[tokio::test]
     async fn simple_send_and_receive() {
-        let (normal_tx, normal_rx) = mpsc::channel(1);
-        let (high_tx, high_rx) = mpsc::channel(1);
-
-        let mailbox: Mailbox<MyActor> = Mailbox::new(normal_tx, high_tx);
-        let mut inbox: Inbox<MyActor> = Inbox::new(normal_rx, high_rx);
+        let (mailbox, mut inbox) = make_mailbox::<MyActor>();
 
         tokio::spawn(async move {
             let _ = mailbox.send(Message::Ping).await;
@@ -156,12 +850,8 @@ mod test {
 
     #[tokio::test]
     async fn send_and_receive_message_with_priority() {
-        let (normal_tx, normal_rx) = mpsc::channel(1);
-        let (high_tx, high_rx) = mpsc::channel(1);
-
-        let mailbox: Mailbox<MyActor> = Mailbox::new(normal_tx, high_tx);
+        let (mailbox, mut inbox) = make_mailbox::<MyActor>();
         let mailbox_2 = mailbox.clone();
-        let mut inbox: Inbox<MyActor> = Inbox::new(normal_rx, high_rx);
 
         tokio::spawn(async move {
             let _ = mailbox.send(Message::Ping).await;
@@ -181,4 +871,338 @@ mod test {
         assert_eq!(2, messages.len());
         assert_eq!(vec![Message::Alert, Message::Ping,], messages);
     }
+
+    #[tokio::test(start_paused = true)]
+    async fn ask_times_out_when_message_is_not_handled() {
+        let (mailbox, _inbox) = make_mailbox::<MyActor>();
+
+        assert!(matches!(
+            mailbox
+                .ask::<()>(Message::Ping, Duration::from_secs(1))
+                .await,
+            Err(ActorError::Timeout)
+        ));
+    }
+
+    #[tokio::test]
+    async fn ask_fails_when_actor_is_gone() {
+        let (mailbox, inbox) = make_mailbox::<MyActor>();
+        drop(inbox);
+
+        assert!(matches!(
+            mailbox
+                .ask::<()>(Message::Ping, Duration::from_secs(1))
+                .await,
+            Err(ActorError::Gone)
+        ));
+    }
+
+    fn bounded(overflow: Overflow) -> (Mailbox<MyActor>, Inbox<MyActor>) {
+        make_mailbox_with_options(MailboxOptions {
+            capacity: 2,
+            overflow,
+            ..Default::default()
+        })
+    }
+
+    async fn drain(mailbox: Mailbox<MyActor>, mut inbox: Inbox<MyActor>) -> Vec<Message> {
+        drop(mailbox);
+
+        let mut messages = Vec::new();
+        while let Some(message) = inbox.recv().await {
+            messages.push(message);
+        }
+        messages
+    }
+
+    #[tokio::test]
+    async fn reject_when_mailbox_is_full() {
+        let (mailbox, inbox) = bounded(Overflow::Reject);
+
+        mailbox.send(Message::Ping).await.unwrap();
+        mailbox.send(Message::Ping).await.unwrap();
+
+        assert!(matches!(
+            mailbox.send(Message::Alert).await,
+            Err(ActorError::MailboxFull)
+        ));
+        assert_eq!(
+            vec![Message::Ping, Message::Ping],
+            drain(mailbox, inbox).await
+        );
+    }
+
+    #[tokio::test]
+    async fn drop_oldest_when_mailbox_is_full() {
+        let (mailbox, inbox) = bounded(Overflow::DropOldest);
+
+        mailbox.send(Message::Ping).await.unwrap();
+        mailbox.send(Message::Ping).await.unwrap();
+        mailbox.send(Message::Alert).await.unwrap();
+
+        assert_eq!(
+            vec![Message::Ping, Message::Alert],
+            drain(mailbox, inbox).await
+        );
+    }
+
+    #[tokio::test]
+    async fn drop_newest_when_mailbox_is_full() {
+        let (mailbox, inbox) = bounded(Overflow::DropNewest);
+
+        mailbox.send(Message::Ping).await.unwrap();
+        mailbox.send(Message::Ping).await.unwrap();
+        mailbox.send(Message::Alert).await.unwrap();
+
+        assert_eq!(
+            vec![Message::Ping, Message::Ping],
+            drain(mailbox, inbox).await
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn block_until_the_actor_makes_room() {
+        let (mailbox, mut inbox) = bounded(Overflow::Block);
+
+        mailbox.send(Message::Ping).await.unwrap();
+        mailbox.send(Message::Ping).await.unwrap();
+
+        let blocked = tokio::time::timeout(Duration::from_secs(1), mailbox.send(Message::Alert));
+        assert!(blocked.await.is_err());
+
+        let sender = mailbox.clone();
+        let handle = tokio::spawn(async move { sender.send(Message::Alert).await });
+        assert_eq!(Some(Message::Ping), inbox.recv().await);
+        handle.await.unwrap().unwrap();
+
+        assert_eq!(
+            vec![Message::Ping, Message::Alert],
+            drain(mailbox, inbox).await
+        );
+    }
+
+    #[tokio::test]
+    async fn try_send_does_not_wait_for_room() {
+        let (mailbox, _inbox) = bounded(Overflow::Block);
+
+        mailbox.try_send(Message::Ping).unwrap();
+        mailbox.try_send(Message::Ping).unwrap();
+
+        assert!(matches!(
+            mailbox.try_send(Message::Ping),
+            Err(ActorError::MailboxFull)
+        ));
+    }
+
+    #[tokio::test]
+    async fn recv_returns_none_once_every_mailbox_is_dropped() {
+        let (mailbox, inbox) = make_mailbox::<MyActor>();
+        let mailbox_2 = mailbox.clone();
+
+        mailbox.send(Message::Ping).await.unwrap();
+        drop(mailbox_2);
+
+        assert_eq!(vec![Message::Ping], drain(mailbox, inbox).await);
+    }
+
+    #[tokio::test]
+    async fn close_rejects_new_messages_and_drains_queued_ones() {
+        let (mailbox, mut inbox) = make_mailbox::<MyActor>();
+
+        mailbox.send(Message::Ping).await.unwrap();
+        inbox.close();
+
+        assert!(matches!(
+            mailbox.send(Message::Alert).await,
+            Err(ActorError::Send(_))
+        ));
+        assert_eq!(Some(Message::Ping), inbox.recv().await);
+        assert_eq!(None, inbox.recv().await);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn skip_messages_queued_past_their_deadline() {
+        let (mailbox, mut inbox) = make_mailbox::<MyActor>();
+
+        mailbox
+            .send_with_envelop(Envelop::normal(Message::Ping).with_ttl(Duration::from_secs(1)))
+            .await
+            .unwrap();
+        mailbox
+            .send_with_envelop(Envelop::normal(Message::Alert).with_ttl(Duration::from_secs(60)))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_secs(2)).await;
+
+        assert_eq!(Some(Message::Alert), inbox.recv().await);
+        assert_eq!(1, mailbox.expired());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn ask_fails_when_message_expires_in_the_queue() {
+        let (mailbox, mut inbox) = make_mailbox::<MyActor>();
+
+        let ask = tokio::spawn({
+            let mailbox = mailbox.clone();
+            async move {
+                mailbox
+                    .ask_with_envelop::<()>(
+                        Envelop::normal(Message::Ping).with_ttl(Duration::from_secs(1)),
+                        Duration::from_secs(10),
+                    )
+                    .await
+            }
+        });
+        tokio::time::sleep(Duration::from_secs(2)).await;
+
+        assert!(inbox.try_recv_envelop().is_none());
+        assert!(matches!(ask.await.unwrap(), Err(ActorError::Expired)));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reject_message_sent_past_its_deadline() {
+        let (mailbox, _inbox) = make_mailbox::<MyActor>();
+        let envelop = Envelop::normal(Message::Ping).with_deadline(Instant::now());
+
+        assert!(matches!(
+            mailbox.send_with_envelop(envelop).await,
+            Err(ActorError::Expired)
+        ));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stop_waiting_for_room_at_the_deadline() {
+        let (mailbox, _inbox) = bounded(Overflow::Block);
+        mailbox.send(Message::Ping).await.unwrap();
+        mailbox.send(Message::Ping).await.unwrap();
+
+        let envelop = Envelop::normal(Message::Alert).with_ttl(Duration::from_secs(1));
+
+        assert!(matches!(
+            mailbox.send_with_envelop(envelop).await,
+            Err(ActorError::Expired)
+        ));
+    }
+
+    fn with_fairness(fairness: Fairness) -> (Mailbox<MyActor>, Inbox<MyActor>) {
+        make_mailbox_with_options(MailboxOptions {
+            capacity: 64,
+            fairness,
+            ..Default::default()
+        })
+    }
+
+    fn flood(mailbox: &Mailbox<MyActor>, priority: Priority, count: usize) {
+        for _ in 0..count {
+            mailbox
+                .try_send_with_envelop(Envelop::with_priority(Message::Ping, priority))
+                .unwrap();
+        }
+    }
+
+    /// Priorities of the messages in the order the inbox serves them.
+    fn served(inbox: &mut Inbox<MyActor>) -> Vec<Priority> {
+        std::iter::from_fn(|| inbox.try_recv_envelop())
+            .map(|envelop| envelop.priority())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn weighted_round_serves_every_priority() {
+        let (mailbox, mut inbox) = with_fairness(Fairness::Weighted);
+
+        for priority in PRIORITIES {
+            flood(&mailbox, priority, 20);
+        }
+
+        let served = served(&mut inbox);
+        let first_round: Vec<_> = PRIORITIES
+            .iter()
+            .flat_map(|priority| std::iter::repeat(*priority).take(priority.weight()))
+            .collect();
+        assert_eq!(first_round, served[..15]);
+        assert_eq!(first_round, served[15..30]);
+    }
+
+    #[tokio::test]
+    async fn low_priority_waits_a_bounded_number_of_messages() {
+        let (mailbox, mut inbox) = with_fairness(Fairness::Weighted);
+
+        flood(&mailbox, Priority::Critical, 60);
+        flood(&mailbox, Priority::High, 60);
+        flood(&mailbox, Priority::Normal, 60);
+        mailbox
+            .try_send_with_envelop(Envelop::with_priority(Message::Alert, Priority::Low))
+            .unwrap();
+
+        let position = std::iter::from_fn(|| inbox.try_recv_envelop())
+            .position(|envelop| *envelop.message() == Message::Alert)
+            .unwrap();
+        assert!(
+            position <= 14,
+            "low message served after {} others",
+            position
+        );
+    }
+
+    #[tokio::test]
+    async fn normal_priority_progresses_under_a_steady_stream_of_high() {
+        let (mailbox, mut inbox) = with_fairness(Fairness::Weighted);
+
+        flood(&mailbox, Priority::High, 10);
+        mailbox.try_send(Message::Alert).unwrap();
+
+        let mut served = 0;
+        loop {
+            let envelop = inbox.try_recv_envelop().unwrap();
+            if *envelop.message() == Message::Alert {
+                break;
+            }
+
+            served += 1;
+            assert!(served <= Priority::High.weight(), "normal message starved");
+            flood(&mailbox, Priority::High, 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn strict_fairness_serves_highest_priority_first() {
+        let (mailbox, mut inbox) = with_fairness(Fairness::Strict);
+
+        flood(&mailbox, Priority::Low, 2);
+        flood(&mailbox, Priority::High, 2);
+        flood(&mailbox, Priority::Critical, 2);
+        flood(&mailbox, Priority::Normal, 2);
+
+        assert_eq!(
+            vec![
+                Priority::Critical,
+                Priority::Critical,
+                Priority::High,
+                Priority::High,
+                Priority::Normal,
+                Priority::Normal,
+                Priority::Low,
+                Priority::Low,
+            ],
+            served(&mut inbox)
+        );
+    }
+
+    #[tokio::test]
+    async fn report_depth_of_every_priority() {
+        let (mailbox, _inbox) = with_fairness(Fairness::Weighted);
+
+        flood(&mailbox, Priority::Low, 1);
+        flood(&mailbox, Priority::Critical, 3);
+
+        assert_eq!(
+            QueueDepth {
+                low: 1,
+                critical: 3,
+                ..Default::default()
+            },
+            mailbox.depth()
+        );
+    }
 }
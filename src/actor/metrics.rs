@@ -0,0 +1,152 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::registry::ActorStatus;
+
+/// Upper bounds, in microseconds, of the `handle` latency histogram buckets.
+const LATENCY_BUCKETS: [u64; 6] = [100, 1_000, 10_000, 100_000, 1_000_000, 10_000_000];
+
+/// Counters updated by the execution loop of a single actor.
+#[derive(Debug, Default)]
+pub(super) struct ActorMetrics {
+    messages: AtomicU64,
+    errors: AtomicU64,
+    restarts: AtomicU64,
+    latency_sum: AtomicU64,
+    latency_buckets: [AtomicU64; LATENCY_BUCKETS.len() + 1],
+}
+
+impl ActorMetrics {
+    pub fn record_handled(&self, latency: Duration, failed: bool) {
+        let micros = latency.as_micros().min(u64::MAX as u128) as u64;
+        let bucket = LATENCY_BUCKETS
+            .iter()
+            .position(|bound| micros <= *bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+
+        self.messages.fetch_add(1, Ordering::Relaxed);
+        self.latency_sum.fetch_add(micros, Ordering::Relaxed);
+        self.latency_buckets[bucket].fetch_add(1, Ordering::Relaxed);
+
+        if failed {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn record_restart(&self) {
+        self.restarts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(
+        &self,
+        name: &str,
+        id: Uuid,
+        status: ActorStatus,
+        queue: QueueDepth,
+        expired: u64,
+    ) -> ActorMetricsSnapshot {
+        let upper_bounds = LATENCY_BUCKETS
+            .iter()
+            .map(|bound| Some(*bound))
+            .chain([None]);
+        let buckets = upper_bounds
+            .zip(self.latency_buckets.iter())
+            .map(|(le_micros, count)| LatencyBucket {
+                le_micros,
+                count: count.load(Ordering::Relaxed),
+            })
+            .collect();
+
+        ActorMetricsSnapshot {
+            name: name.to_string(),
+            id,
+            status,
+            queue,
+            messages: self.messages.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            restarts: self.restarts.load(Ordering::Relaxed),
+            expired,
+            latency: LatencySnapshot {
+                sum_micros: self.latency_sum.load(Ordering::Relaxed),
+                buckets,
+            },
+        }
+    }
+}
+
+/// Messages waiting in a mailbox, per priority.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, ToSchema)]
+pub struct QueueDepth {
+    pub low: usize,
+    pub normal: usize,
+    pub high: usize,
+    pub critical: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct LatencyBucket {
+    /// Inclusive upper bound of the bucket, `None` for the overflow bucket.
+    pub le_micros: Option<u64>,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct LatencySnapshot {
+    pub sum_micros: u64,
+    pub buckets: Vec<LatencyBucket>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct ActorMetricsSnapshot {
+    pub name: String,
+    pub id: Uuid,
+    pub status: ActorStatus,
+    pub queue: QueueDepth,
+    pub messages: u64,
+    pub errors: u64,
+    pub restarts: u64,
+    /// Messages discarded unhandled because their deadline passed.
+    pub expired: u64,
+    pub latency: LatencySnapshot,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn record_latency_in_matching_bucket() {
+        let metrics = ActorMetrics::default();
+
+        metrics.record_handled(Duration::from_micros(50), false);
+        metrics.record_handled(Duration::from_millis(5), true);
+        metrics.record_handled(Duration::from_secs(60), false);
+
+        let snapshot = metrics.snapshot(
+            "actor",
+            Uuid::new_v4(),
+            ActorStatus::Running,
+            QueueDepth::default(),
+            0,
+        );
+
+        assert_eq!(3, snapshot.messages);
+        assert_eq!(1, snapshot.errors);
+        assert_eq!(
+            vec![1, 0, 1, 0, 0, 0, 1],
+            snapshot
+                .latency
+                .buckets
+                .iter()
+                .map(|bucket| bucket.count)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(None, snapshot.latency.buckets.last().unwrap().le_micros);
+    }
+}
@@ -1,14 +1,72 @@
 #![allow(unused)]
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
 use async_trait::async_trait;
-use mailbox::{make_mailbox, Inbox, Mailbox};
-use tokio::{sync::mpsc::error::SendError, task::JoinHandle};
+use bus::{EventBus, Forwarders, Lag, Topic};
+use child::Children;
+use dead_letter::DeadLetterReason;
+use dead_letter::{DeadLetter, DeadLetters};
+use mailbox::{
+    current_correlation_id, make_mailbox, with_correlation_id, Envelop, Inbox, Mailbox,
+    MailboxOptions, ReplyTo, WeakMailbox,
+};
+use metrics::{ActorMetrics, ActorMetricsSnapshot};
+use panic::{panic_message, CatchUnwind, PanicPolicy};
+use registry::{ActorInfo, ActorStatus, Registry};
+use stash::{Stash, StashOptions};
+use supervisor::{Control, Supervisor, SupervisorEvent, SupervisorOptions};
+use timer::{TimerHandle, Timers};
+use tokio::{
+    sync::{
+        broadcast,
+        mpsc::{self, error::SendError},
+    },
+    task::JoinHandle,
+};
+use tracing::{error, info, info_span, Instrument};
 use uuid::Uuid;
 
+pub mod bus;
+mod child;
+pub mod dead_letter;
+pub mod journal;
 pub mod mailbox;
+pub mod metrics;
+pub mod panic;
+pub mod persistence;
+pub mod registry;
+pub mod stash;
+pub mod supervisor;
+#[cfg(test)]
+pub mod testkit;
+pub mod timer;
 
 #[derive(Debug, Clone)]
 pub enum ActorError {
     Send(String),
+    Handler(String),
+    /// The handler of `actor` panicked while handling `message`, its `Debug`
+    /// rendering.
+    Panicked {
+        actor: String,
+        message: String,
+        panic: String,
+    },
+    Timeout,
+    Gone,
+    NoReply,
+    UnexpectedReply,
+    MailboxFull,
+    StashFull,
+    Expired,
+    /// The journal of a persistent actor could not be read or written.
+    Journal(String),
+    AlreadyRegistered(String),
+    NotFound(String),
+    TopicMismatch(String),
     Quit,
 }
 
@@ -17,32 +75,389 @@ impl std::fmt::Display for ActorError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ActorError::Send(s) => write!(f, "Send error: {}", s),
+            ActorError::Handler(s) => write!(f, "handler error: {}", s),
+            ActorError::Panicked {
+                actor,
+                message,
+                panic,
+            } => write!(
+                f,
+                "actor `{}` panicked handling {}: {}",
+                actor, message, panic
+            ),
+            ActorError::Timeout => write!(f, "timed out waiting for a reply"),
+            ActorError::Gone => write!(f, "actor is gone"),
+            ActorError::NoReply => write!(f, "actor did not reply"),
+            ActorError::UnexpectedReply => write!(f, "unexpected reply type"),
+            ActorError::MailboxFull => write!(f, "mailbox is full"),
+            ActorError::StashFull => write!(f, "stash is full"),
+            ActorError::Expired => write!(f, "message deadline passed"),
+            ActorError::Journal(s) => write!(f, "journal error: {}", s),
+            ActorError::AlreadyRegistered(name) => write!(f, "actor `{}` already registered", name),
+            ActorError::NotFound(name) => write!(f, "actor `{}` not found", name),
+            ActorError::TopicMismatch(topic) => {
+                write!(f, "topic `{}` carries another event type", topic)
+            }
             ActorError::Quit => write!(f, "quit"),
         }
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct Context {
+pub struct Context<A: Actor> {
     id: Uuid,
+    name: Arc<str>,
+    mailbox: WeakMailbox<A>,
+    /// Envelop of the message being handled, without the message.
+    handling: Arc<Mutex<Option<Envelop<()>>>>,
+    stash: Stash<A::Message>,
+    timers: Timers,
+    forwarders: Forwarders,
+    children: Children,
+    workspace: Workspace,
+    metrics: Arc<ActorMetrics>,
+}
+
+impl<A: Actor> std::fmt::Debug for Context<A> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Context")
+            .field("id", &self.id)
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<A: Actor> Clone for Context<A> {
+    fn clone(&self) -> Self {
+        Self {
+            id: self.id,
+            name: self.name.clone(),
+            mailbox: self.mailbox.clone(),
+            handling: self.handling.clone(),
+            stash: self.stash.clone(),
+            timers: self.timers.clone(),
+            forwarders: self.forwarders.clone(),
+            children: self.children.clone(),
+            workspace: self.workspace.clone(),
+            metrics: self.metrics.clone(),
+        }
+    }
+}
+
+impl<A: Actor> Context<A> {
+    /// `supervisor` is the one of the actor, its children get their own scope
+    /// under it.
+    fn new(
+        name: &str,
+        mailbox: &Mailbox<A>,
+        workspace: &Workspace,
+        supervisor: &Supervisor,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name: name.into(),
+            mailbox: mailbox.downgrade(),
+            handling: Arc::new(Mutex::new(None)),
+            stash: Stash::new(A::stash_options()),
+            timers: Timers::default(),
+            forwarders: Forwarders::default(),
+            children: Children::new(supervisor.scope(), workspace.registry.clone()),
+            workspace: workspace.clone(),
+            metrics: Arc::new(ActorMetrics::default()),
+        }
+    }
+
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns this actor's own mailbox, `None` once it stopped accepting
+    /// messages.
+    pub fn mailbox(&self) -> Option<Mailbox<A>> {
+        self.mailbox.upgrade()
+    }
+
+    /// Correlation id of the message being handled, messages sent by the
+    /// handler carry the same id.
+    pub fn correlation_id(&self) -> Option<Uuid> {
+        current_correlation_id()
+    }
+
+    /// Delivers `message` to this actor's mailbox once `delay` elapsed.
+    pub fn send_after(&self, delay: Duration, message: A::Message) -> TimerHandle
+    where
+        A: 'static,
+    {
+        self.timers.send_after(self.mailbox.clone(), delay, message)
+    }
+
+    /// Delivers `message` to this actor's mailbox every `period`, the first
+    /// delivery happens after one period.
+    pub fn send_interval(&self, period: Duration, message: A::Message) -> TimerHandle
+    where
+        A: 'static,
+        A::Message: Clone,
+    {
+        self.timers
+            .send_interval(self.mailbox.clone(), period, message)
+    }
+
+    pub fn bus(&self) -> &EventBus {
+        &self.workspace.bus
+    }
+
+    /// Spawns `actor` registered as `<parent>/<name>`, it is stopped, after
+    /// draining its inbox, when this actor instance stops.
+    pub fn spawn_child<C: Actor + 'static>(
+        &self,
+        name: &str,
+        actor: C,
+    ) -> Result<Mailbox<C>, ActorError> {
+        let mut actor = Some(actor);
+        self.adopt(name, move || actor.take(), false)
+    }
+
+    /// Same as `spawn_child` for an actor restarted by the supervisor. The
+    /// restart strategy only applies among the children of this actor.
+    pub fn spawn_supervised_child<C, F>(
+        &self,
+        name: &str,
+        factory: F,
+    ) -> Result<Mailbox<C>, ActorError>
+    where
+        C: Actor + 'static,
+        F: Fn() -> C + Send + 'static,
+    {
+        self.adopt(name, move || Some(factory()), true)
+    }
+
+    /// Delivers `message` to this actor's mailbox once the actor registered
+    /// as `name` stopped. The watch ends when this actor instance stops.
+    pub fn watch(&self, name: &str, message: A::Message) -> Result<(), ActorError>
+    where
+        A: 'static,
+    {
+        let mut events = self.workspace.supervisor.subscribe();
+        let registry = self.workspace.registry.clone();
+        let watched = registry
+            .id(name)
+            .ok_or_else(|| ActorError::NotFound(name.to_string()))?;
+        let mailbox = self.mailbox.clone();
+
+        self.forwarders.spawn(async move {
+            loop {
+                match events.recv().await {
+                    Ok(SupervisorEvent::Stopped { id }) if id == watched => break,
+                    Ok(_) => {}
+                    Err(broadcast::error::RecvError::Lagged(_)) if !registry.contains(watched) => {
+                        break
+                    }
+                    Err(broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(broadcast::error::RecvError::Closed) => return,
+                }
+            }
+
+            if let Some(mailbox) = mailbox.upgrade() {
+                let _ = mailbox.send(message).await;
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Forwards the events of `topic` to this actor's mailbox until the actor
+    /// instance stops, subscribe from `started` to keep receiving them after a
+    /// restart.
+    pub fn subscribe<T, F>(&self, topic: &Topic<T>, lag: Lag, map: F) -> Result<(), ActorError>
+    where
+        A: 'static,
+        T: Clone + Send + 'static,
+        F: Fn(T) -> A::Message + Send + 'static,
+    {
+        let subscription = self.workspace.bus.subscribe(topic, lag)?;
+        self.forwarders
+            .forward(subscription, self.mailbox.clone(), map);
+        Ok(())
+    }
+
+    /// Answers the message currently handled, a no-op when the message was
+    /// not sent with `Mailbox::ask`. The asker receives
+    /// `ActorError::UnexpectedReply` when it expects another type than `R`.
+    pub fn reply<R: Send + 'static>(&self, reply: R) {
+        if let Some(reply_to) = self.take_reply_to() {
+            reply_to.send(Ok(Box::new(reply)));
+        }
+    }
+
+    /// Defers `message`, the one being handled, until `unstash_all`. A reply
+    /// expected by its sender is deferred with it.
+    pub fn stash(&self, message: A::Message) -> Result<(), ActorError> {
+        let envelop = match self.handling.lock().unwrap().take() {
+            Some(handling) => handling.seal(message),
+            None => Envelop::normal(message),
+        };
+
+        match self.stash.push(envelop) {
+            Ok(None) => Ok(()),
+            Ok(Some(evicted)) => {
+                self.discard_stashed(evicted);
+                Ok(())
+            }
+            Err(refused) => {
+                self.discard_stashed(refused);
+                Err(ActorError::StashFull)
+            }
+        }
+    }
+
+    /// Gives every stashed message back to the actor, in the order they were
+    /// stashed and before any other queued message. Returns their number.
+    pub fn unstash_all(&self) -> usize {
+        let stashed = self.stash.drain();
+        let count = stashed.len();
+
+        self.mailbox.replay(stashed);
+        count
+    }
+
+    pub fn stashed(&self) -> usize {
+        self.stash.len()
+    }
+
+    fn adopt<C, F>(
+        &self,
+        name: &str,
+        factory: F,
+        restartable: bool,
+    ) -> Result<Mailbox<C>, ActorError>
+    where
+        C: Actor + 'static,
+        F: FnMut() -> Option<C> + Send + 'static,
+    {
+        let name = format!("{}/{}", self.name, name);
+        let (id, mailbox, handle) =
+            self.workspace
+                .spawn_child(&name, factory, self.children.supervisor(), restartable)?;
+        self.children.track(id, &name, handle, mailbox.clone());
+
+        Ok(mailbox)
+    }
+
+    fn discard_stashed(&self, mut envelop: Envelop<A::Message>) {
+        if let Some(reply_to) = envelop.take_reply_to() {
+            reply_to.send(Err(ActorError::StashFull));
+        }
+
+        self.mailbox.dead_letter(envelop, DeadLetterReason::Dropped);
+    }
+
+    fn take_reply_to(&self) -> Option<ReplyTo> {
+        self.handling
+            .lock()
+            .unwrap()
+            .as_mut()
+            .and_then(Envelop::take_reply_to)
+    }
+
+    fn expect_reply(&self, handling: Envelop<()>) {
+        *self.handling.lock().unwrap() = Some(handling);
+    }
+
+    fn unanswered(&self) {
+        if let Some(reply_to) = self.take_reply_to() {
+            reply_to.send(Err(ActorError::NoReply));
+        }
+        self.handling.lock().unwrap().take();
+    }
+}
+
+/// Why an actor instance stopped handling messages.
+#[derive(Debug, Clone)]
+pub enum StopReason {
+    /// The actor asked to stop with `ActorError::Quit`.
+    Quit,
+    /// A hook or handler returned an error.
+    Failed(ActorError),
+    /// Every mailbox of the actor was dropped.
+    Closed,
+    /// The supervisor restarts the actor because a sibling failed.
+    Restart,
+    /// The workspace is shutting down.
+    Shutdown,
 }
 
-impl Context {
-    pub fn new() -> Self {
-        Self { id: Uuid::new_v4() }
+impl std::fmt::Display for StopReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StopReason::Quit => write!(f, "quit"),
+            StopReason::Failed(e) => write!(f, "failed: {}", e),
+            StopReason::Closed => write!(f, "mailbox closed"),
+            StopReason::Restart => write!(f, "restart"),
+            StopReason::Shutdown => write!(f, "workspace shutdown"),
+        }
     }
 }
 
 #[async_trait]
-pub trait Actor: Send {
+pub trait Actor: Send + Sized {
     type Message: std::fmt::Debug + Send + Sync + 'static;
 
-    async fn handle(&mut self, ctx: &Context, message: Self::Message) -> Result<(), ActorError>;
+    /// Capacity and overflow policy of the mailbox created when spawning the
+    /// actor.
+    fn mailbox_options() -> MailboxOptions {
+        MailboxOptions::default()
+    }
+
+    /// Size of the stash and what `Context::stash` does once it is full.
+    fn stash_options() -> StashOptions {
+        StashOptions::default()
+    }
+
+    /// Whether a panic in `handle` or `started` restarts the siblings of the
+    /// actor like any failure or only resets the actor itself.
+    fn panic_policy() -> PanicPolicy {
+        PanicPolicy::default()
+    }
+
+    /// Called before the first message is handled, an error stops the actor
+    /// like a failed handler would.
+    async fn started(&mut self, _ctx: &Context<Self>) -> Result<(), ActorError> {
+        Ok(())
+    }
+
+    async fn handle(
+        &mut self,
+        ctx: &Context<Self>,
+        message: Self::Message,
+    ) -> Result<(), ActorError>;
+
+    /// Called once the actor stopped handling messages, including before
+    /// being replaced by a restart.
+    async fn stopped(&mut self, _ctx: &Context<Self>, _reason: &StopReason) {}
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+pub struct ShutdownReport {
+    /// Actors that did not stop before the deadline.
+    pub unfinished: Vec<ActorInfo>,
+}
+
+impl ShutdownReport {
+    pub fn is_complete(&self) -> bool {
+        self.unfinished.is_empty()
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Workspace {
     name: String,
+    supervisor: Supervisor,
+    registry: Registry,
+    bus: EventBus,
+    dead_letters: DeadLetters,
 }
 
 impl Default for Workspace {
@@ -53,27 +468,314 @@ impl Default for Workspace {
 
 impl Workspace {
     pub fn new<S: Into<String>>(name: S) -> Self {
-        Self { name: name.into() }
+        Self::with_options(name, SupervisorOptions::default())
+    }
+
+    pub fn with_options<S: Into<String>>(name: S, options: SupervisorOptions) -> Self {
+        let bus = EventBus::default();
+
+        Self {
+            name: name.into(),
+            supervisor: Supervisor::new(options),
+            registry: Registry::default(),
+            dead_letters: DeadLetters::new(bus.clone()),
+            bus,
+        }
+    }
+
+    /// Spawns an actor that is not restarted when its handler fails.
+    ///
+    /// The registry does not keep the actor alive: it stops with
+    /// `StopReason::Closed` once the returned mailbox and every looked up one
+    /// are dropped, keep the mailbox for as long as the actor should run.
+    #[must_use = "the actor stops once the returned mailbox is dropped"]
+    pub fn spawn<A: Actor + 'static>(
+        &self,
+        name: &str,
+        actor: A,
+    ) -> Result<(Mailbox<A>, JoinHandle<()>), ActorError> {
+        let mut actor = Some(actor);
+        let (_, mailbox, handle) =
+            self.spawn_child(name, move || actor.take(), &self.supervisor, false)?;
+        Ok((mailbox, handle))
     }
 
-    pub fn spawn<A: Actor + 'static>(&self, actor: A) -> (Mailbox<A>, JoinHandle<()>) {
+    /// Spawns an actor built by `factory`, a fresh instance is created each
+    /// time the supervisor restarts it. The mailbox survives restarts, the
+    /// actor stops like with `spawn` once it is dropped.
+    #[must_use = "the actor stops once the returned mailbox is dropped"]
+    pub fn spawn_supervised<A, F>(
+        &self,
+        name: &str,
+        factory: F,
+    ) -> Result<(Mailbox<A>, JoinHandle<()>), ActorError>
+    where
+        A: Actor + 'static,
+        F: Fn() -> A + Send + 'static,
+    {
+        let (_, mailbox, handle) =
+            self.spawn_child(name, move || Some(factory()), &self.supervisor, true)?;
+        Ok((mailbox, handle))
+    }
+
+    pub fn bus(&self) -> EventBus {
+        self.bus.clone()
+    }
+
+    pub fn events(&self) -> broadcast::Receiver<SupervisorEvent> {
+        self.supervisor.subscribe()
+    }
+
+    /// Most recent messages that never reached their actor, oldest first.
+    /// New ones are published on `dead_letter::DEAD_LETTERS`.
+    pub fn dead_letters(&self) -> Vec<DeadLetter> {
+        self.dead_letters.list()
+    }
+
+    /// Returns the mailbox of the live actor registered as `name`, `None` when
+    /// no such actor exists, when it is not an `A` or when every mailbox of the
+    /// actor was dropped.
+    pub fn lookup<A: Actor + 'static>(&self, name: &str) -> Option<Mailbox<A>> {
+        self.registry.lookup(name)
+    }
+
+    pub fn actors(&self) -> Vec<ActorInfo> {
+        self.registry.actors()
+    }
+
+    /// Snapshot of the runtime metrics of every live actor.
+    pub fn metrics(&self) -> Vec<ActorMetricsSnapshot> {
+        self.registry.metrics()
+    }
+
+    /// Stops accepting messages, lets every actor drain its inbox and run its
+    /// `stopped` hook, and reports the actors still running after `deadline`.
+    pub async fn shutdown(&self, deadline: Duration) -> ShutdownReport {
+        info!("shutting down workspace `{}`", self.name);
+
+        self.supervisor
+            .shutdown(tokio::time::Instant::now() + deadline)
+            .await;
+
+        ShutdownReport {
+            unfinished: self.registry.actors(),
+        }
+    }
+
+    fn spawn_child<A, F>(
+        &self,
+        name: &str,
+        factory: F,
+        supervisor: &Supervisor,
+        restartable: bool,
+    ) -> Result<(Uuid, Mailbox<A>, JoinHandle<()>), ActorError>
+    where
+        A: Actor + 'static,
+        F: FnMut() -> Option<A> + Send + 'static,
+    {
         let (mailbox, inbox) = make_mailbox();
+        mailbox.route_dead_letters(name, self.dead_letters.clone());
+        let ctx = Context::new(name, &mailbox, self, supervisor);
+        let id = ctx.id;
+
+        self.registry
+            .register(name, ctx.id, &mailbox, ctx.metrics.clone())?;
 
-        let ctx = Context::new();
-        let handle = tokio::spawn(execution_loop(actor, inbox, ctx.clone()));
+        let control = supervisor.register(ctx.id, restartable);
 
-        (mailbox, handle)
+        let handle = tokio::spawn(supervision_loop(
+            factory,
+            inbox,
+            ctx,
+            control,
+            supervisor.clone(),
+            self.registry.clone(),
+            restartable,
+        ));
+
+        Ok((id, mailbox, handle))
     }
 }
 
-async fn execution_loop<A: Actor>(mut actor: A, mut inbox: Inbox<A>, ctx: Context) {
-    while let Some(message) = inbox.recv().await {
-        match actor.handle(&ctx, message).await {
-            Ok(_) => {}
-            Err(ActorError::Quit) => return,
-            Err(_) => return,
+async fn supervision_loop<A, F>(
+    mut factory: F,
+    mut inbox: Inbox<A>,
+    ctx: Context<A>,
+    mut control: mpsc::UnboundedReceiver<Control>,
+    supervisor: Supervisor,
+    registry: Registry,
+    restartable: bool,
+) where
+    A: Actor,
+    F: FnMut() -> Option<A>,
+{
+    let id = ctx.id;
+
+    while let Some(mut actor) = factory() {
+        let reason = execution_loop(&mut actor, &mut inbox, &ctx, &mut control).await;
+        ctx.children.stop_all().await;
+        stop(&mut actor, &ctx, &reason).await;
+        ctx.timers.cancel_all();
+        ctx.forwarders.cancel_all();
+        ctx.unstash_all();
+
+        match reason {
+            StopReason::Quit | StopReason::Closed | StopReason::Shutdown => break,
+            StopReason::Restart => {
+                ctx.metrics.record_restart();
+                supervisor.emit(SupervisorEvent::Restarted { id });
+            }
+            StopReason::Failed(error) => {
+                let panicked = matches!(error, ActorError::Panicked { .. });
+                supervisor.emit(SupervisorEvent::Failed { id, error });
+
+                if !restartable || supervisor.is_shutting_down() {
+                    break;
+                }
+
+                let Some(restarts) = supervisor.record_restart() else {
+                    supervisor.emit(SupervisorEvent::GaveUp { id });
+                    break;
+                };
+
+                if !panicked || A::panic_policy() != PanicPolicy::Reset {
+                    supervisor.restart_siblings(id);
+                }
+                registry.set_status(ctx.name(), ActorStatus::Restarting);
+                tokio::select! {
+                    _ = tokio::time::sleep(supervisor.backoff(restarts)) => {}
+                    Some(Control::Shutdown) = control.recv() => break,
+                }
+                registry.set_status(ctx.name(), ActorStatus::Running);
+                ctx.metrics.record_restart();
+                supervisor.emit(SupervisorEvent::Restarted { id });
+            }
         }
     }
+
+    registry.deregister(ctx.name(), id);
+    supervisor.deregister(id);
+    supervisor.emit(SupervisorEvent::Stopped { id });
+}
+
+async fn execution_loop<A: Actor>(
+    actor: &mut A,
+    inbox: &mut Inbox<A>,
+    ctx: &Context<A>,
+    control: &mut mpsc::UnboundedReceiver<Control>,
+) -> StopReason {
+    if let Err(error) = start(actor, ctx).await {
+        return stop_reason(error);
+    }
+
+    let mut draining = false;
+
+    loop {
+        let envelop = tokio::select! {
+            biased;
+            Some(control) = control.recv() => match control {
+                Control::Restart if !draining => return StopReason::Restart,
+                Control::Restart => continue,
+                Control::Shutdown => {
+                    inbox.close();
+                    draining = true;
+                    continue;
+                }
+            },
+            envelop = inbox.recv_envelop() => envelop,
+        };
+
+        let Some(envelop) = envelop else {
+            return if draining {
+                StopReason::Shutdown
+            } else {
+                StopReason::Closed
+            };
+        };
+
+        if let Err(error) = handle_envelop(actor, ctx, envelop).await {
+            return stop_reason(error);
+        }
+    }
+}
+
+async fn handle_envelop<A: Actor>(
+    actor: &mut A,
+    ctx: &Context<A>,
+    envelop: Envelop<A::Message>,
+) -> Result<(), ActorError> {
+    let correlation_id = envelop.correlation_id();
+    let span = info_span!(
+        parent: envelop.span(),
+        "handle",
+        actor = ctx.name(),
+        actor_id = %ctx.id,
+        correlation_id = %correlation_id,
+    );
+
+    let (message, handling) = envelop.open();
+    let rendered = format!("{:?}", message);
+    ctx.expect_reply(handling);
+    let started_at = tokio::time::Instant::now();
+    let handled = CatchUnwind::new(actor.handle(ctx, message));
+    let result = with_correlation_id(correlation_id, handled)
+        .instrument(span)
+        .await
+        .unwrap_or_else(|payload| Err(panicked(ctx, rendered, payload.as_ref())));
+    ctx.metrics
+        .record_handled(started_at.elapsed(), is_failure(&result));
+    ctx.unanswered();
+
+    result
+}
+
+/// Runs `started`, a panic fails the actor like an error would.
+async fn start<A: Actor>(actor: &mut A, ctx: &Context<A>) -> Result<(), ActorError> {
+    CatchUnwind::new(actor.started(ctx))
+        .await
+        .unwrap_or_else(|payload| Err(panicked(ctx, "started".to_string(), payload.as_ref())))
+}
+
+/// Runs `stopped`, a panic is only logged since the actor stops anyway.
+async fn stop<A: Actor>(actor: &mut A, ctx: &Context<A>, reason: &StopReason) {
+    if let Err(payload) = CatchUnwind::new(actor.stopped(ctx, reason)).await {
+        error!(
+            "actor `{}` panicked in stopped: {}",
+            ctx.name(),
+            panic_message(payload.as_ref())
+        );
+    }
+}
+
+fn panicked<A: Actor>(
+    ctx: &Context<A>,
+    message: String,
+    payload: &(dyn std::any::Any + Send),
+) -> ActorError {
+    let panic = panic_message(payload);
+    error!(
+        "actor `{}` panicked handling {}: {}",
+        ctx.name(),
+        message,
+        panic
+    );
+
+    ActorError::Panicked {
+        actor: ctx.name().to_string(),
+        message,
+        panic,
+    }
+}
+
+fn is_failure(result: &Result<(), ActorError>) -> bool {
+    !matches!(result, Ok(_) | Err(ActorError::Quit))
+}
+
+fn stop_reason(error: ActorError) -> StopReason {
+    match error {
+        ActorError::Quit => StopReason::Quit,
+        error => StopReason::Failed(error),
+    }
 }
 
 impl<T> From<SendError<T>> for ActorError {
@@ -84,6 +786,7 @@ impl<T> From<SendError<T>> for ActorError {
 
 #[cfg(test)]
 mod test {
+    use supervisor::{Intensity, Strategy};
     use tokio::sync::oneshot;
 
     use super::*;
@@ -93,6 +796,10 @@ mod test {
         Increment,
         Decrement,
         GetCounter(oneshot::Sender<isize>),
+        Count,
+        Crash,
+        Panic,
+        Hang,
         Quit,
     }
 
@@ -107,13 +814,17 @@ mod test {
 
         async fn handle(
             &mut self,
-            _ctx: &Context,
+            ctx: &Context<Self>,
             message: Self::Message,
         ) -> Result<(), ActorError> {
             match message {
                 Message::Increment => self.count += 1,
+                Message::Count => ctx.reply(self.count),
                 Message::Decrement => self.count -= 1,
                 Message::Quit => return Err(ActorError::Quit),
+                Message::Crash => return Err(ActorError::Handler("crash".to_string())),
+                Message::Panic => panic!("boom"),
+                Message::Hang => std::future::pending().await,
                 Message::GetCounter(reply_to) => {
                     let _ = reply_to.send(self.count);
                 }
@@ -127,7 +838,7 @@ mod test {
     async fn send_and_receive_multiple_messages() {
         let workspace = Workspace::default();
         let counter = CounterActor::default();
-        let (mailbox, actor_handle) = workspace.spawn(counter);
+        let (mailbox, actor_handle) = workspace.spawn("counter", counter).unwrap();
 
         let (tx, rx) = oneshot::channel::<isize>();
 
@@ -150,4 +861,755 @@ mod test {
         let v = rx.await.unwrap();
         assert_eq!(2, v);
     }
+
+    async fn counter(mailbox: &Mailbox<CounterActor>) -> isize {
+        let (tx, rx) = oneshot::channel();
+        mailbox.send(Message::GetCounter(tx)).await.unwrap();
+        rx.await.unwrap()
+    }
+
+    async fn wait_for_restarts(events: &mut broadcast::Receiver<SupervisorEvent>, count: usize) {
+        let mut restarts = 0;
+        while restarts < count {
+            if let SupervisorEvent::Restarted { .. } = events.recv().await.unwrap() {
+                restarts += 1;
+            }
+        }
+    }
+
+    fn workspace(strategy: Strategy) -> Workspace {
+        Workspace::with_options(
+            "supervised",
+            SupervisorOptions {
+                strategy,
+                ..Default::default()
+            },
+        )
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn restart_failed_actor_with_fresh_state() {
+        let workspace = workspace(Strategy::OneForOne);
+        let mut events = workspace.events();
+        let (mailbox, _) = workspace
+            .spawn_supervised("counter", CounterActor::default)
+            .unwrap();
+
+        mailbox.send(Message::Increment).await.unwrap();
+        mailbox.send(Message::Crash).await.unwrap();
+
+        assert!(matches!(
+            events.recv().await.unwrap(),
+            SupervisorEvent::Failed {
+                error: ActorError::Handler(_),
+                ..
+            }
+        ));
+        assert!(matches!(
+            events.recv().await.unwrap(),
+            SupervisorEvent::Restarted { .. }
+        ));
+        assert_eq!(0, counter(&mailbox).await);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn one_for_one_keeps_siblings_running() {
+        let workspace = workspace(Strategy::OneForOne);
+        let mut events = workspace.events();
+        let (first, _) = workspace
+            .spawn_supervised("first", CounterActor::default)
+            .unwrap();
+        let (second, _) = workspace
+            .spawn_supervised("second", CounterActor::default)
+            .unwrap();
+
+        second.send(Message::Increment).await.unwrap();
+        first.send(Message::Crash).await.unwrap();
+        wait_for_restarts(&mut events, 1).await;
+
+        assert_eq!(1, counter(&second).await);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn one_for_all_restarts_every_sibling() {
+        let workspace = workspace(Strategy::OneForAll);
+        let mut events = workspace.events();
+        let (first, _) = workspace
+            .spawn_supervised("first", CounterActor::default)
+            .unwrap();
+        let (second, _) = workspace
+            .spawn_supervised("second", CounterActor::default)
+            .unwrap();
+
+        first.send(Message::Increment).await.unwrap();
+        second.send(Message::Increment).await.unwrap();
+        assert_eq!(1, counter(&first).await);
+
+        second.send(Message::Crash).await.unwrap();
+        wait_for_restarts(&mut events, 2).await;
+
+        assert_eq!(0, counter(&first).await);
+        assert_eq!(0, counter(&second).await);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn rest_for_one_restarts_younger_siblings() {
+        let workspace = workspace(Strategy::RestForOne);
+        let mut events = workspace.events();
+        let (first, _) = workspace
+            .spawn_supervised("first", CounterActor::default)
+            .unwrap();
+        let (second, _) = workspace
+            .spawn_supervised("second", CounterActor::default)
+            .unwrap();
+        let (third, _) = workspace
+            .spawn_supervised("third", CounterActor::default)
+            .unwrap();
+
+        for mailbox in [&first, &second, &third] {
+            mailbox.send(Message::Increment).await.unwrap();
+            assert_eq!(1, counter(mailbox).await);
+        }
+
+        second.send(Message::Crash).await.unwrap();
+        wait_for_restarts(&mut events, 2).await;
+
+        assert_eq!(1, counter(&first).await);
+        assert_eq!(0, counter(&second).await);
+        assert_eq!(0, counter(&third).await);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn give_up_when_restart_intensity_is_exceeded() {
+        let workspace = Workspace::with_options(
+            "supervised",
+            SupervisorOptions {
+                intensity: Intensity {
+                    max_restarts: 1,
+                    within: Duration::from_secs(60),
+                },
+                ..Default::default()
+            },
+        );
+        let mut events = workspace.events();
+        let (mailbox, handle) = workspace
+            .spawn_supervised("counter", CounterActor::default)
+            .unwrap();
+
+        mailbox.send(Message::Crash).await.unwrap();
+        wait_for_restarts(&mut events, 1).await;
+        mailbox.send(Message::Crash).await.unwrap();
+
+        handle.await.unwrap();
+        let mut gave_up = false;
+        while let Ok(event) = events.try_recv() {
+            gave_up |= matches!(event, SupervisorEvent::GaveUp { .. });
+        }
+        assert!(gave_up);
+    }
+
+    #[tokio::test]
+    async fn unsupervised_actor_is_not_restarted() {
+        let workspace = Workspace::default();
+        let (mailbox, handle) = workspace.spawn("counter", CounterActor::default()).unwrap();
+
+        mailbox.send(Message::Crash).await.unwrap();
+        handle.await.unwrap();
+
+        assert!(mailbox.send(Message::Increment).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn ask_returns_the_reply() {
+        let workspace = Workspace::default();
+        let (mailbox, _) = workspace.spawn("counter", CounterActor::default()).unwrap();
+
+        mailbox.send(Message::Increment).await.unwrap();
+        let count: isize = mailbox
+            .ask(Message::Count, Duration::from_secs(1))
+            .await
+            .unwrap();
+
+        assert_eq!(1, count);
+    }
+
+    #[tokio::test]
+    async fn ask_fails_when_handler_does_not_reply() {
+        let workspace = Workspace::default();
+        let (mailbox, _) = workspace.spawn("counter", CounterActor::default()).unwrap();
+
+        assert!(matches!(
+            mailbox
+                .ask::<isize>(Message::Increment, Duration::from_secs(1))
+                .await,
+            Err(ActorError::NoReply)
+        ));
+    }
+
+    #[tokio::test]
+    async fn ask_fails_on_unexpected_reply_type() {
+        let workspace = Workspace::default();
+        let (mailbox, _) = workspace.spawn("counter", CounterActor::default()).unwrap();
+
+        assert!(matches!(
+            mailbox
+                .ask::<String>(Message::Count, Duration::from_secs(1))
+                .await,
+            Err(ActorError::UnexpectedReply)
+        ));
+    }
+
+    #[derive(Debug)]
+    struct LifecycleActor {
+        hooks: Arc<Mutex<Vec<String>>>,
+    }
+
+    #[async_trait]
+    impl Actor for LifecycleActor {
+        type Message = Message;
+
+        async fn started(&mut self, _ctx: &Context<Self>) -> Result<(), ActorError> {
+            self.hooks.lock().unwrap().push("started".to_string());
+            Ok(())
+        }
+
+        async fn handle(
+            &mut self,
+            _ctx: &Context<Self>,
+            message: Self::Message,
+        ) -> Result<(), ActorError> {
+            self.hooks.lock().unwrap().push(format!("{:?}", message));
+            match message {
+                Message::Crash => Err(ActorError::Handler("crash".to_string())),
+                Message::Quit => Err(ActorError::Quit),
+                Message::Hang => std::future::pending().await,
+                _ => Ok(()),
+            }
+        }
+
+        async fn stopped(&mut self, _ctx: &Context<Self>, reason: &StopReason) {
+            self.hooks
+                .lock()
+                .unwrap()
+                .push(format!("stopped: {}", reason));
+        }
+    }
+
+    #[tokio::test]
+    async fn lifecycle_hooks_surround_messages() {
+        let hooks = Arc::new(Mutex::new(Vec::new()));
+        let workspace = Workspace::default();
+        let (mailbox, handle) = workspace
+            .spawn(
+                "lifecycle",
+                LifecycleActor {
+                    hooks: hooks.clone(),
+                },
+            )
+            .unwrap();
+
+        mailbox.send(Message::Increment).await.unwrap();
+        mailbox.send(Message::Quit).await.unwrap();
+        handle.await.unwrap();
+
+        assert_eq!(
+            vec!["started", "Increment", "Quit", "stopped: quit"],
+            *hooks.lock().unwrap()
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn lifecycle_hooks_run_for_each_restart() {
+        let hooks = Arc::new(Mutex::new(Vec::new()));
+        let workspace = Workspace::default();
+        let factory_hooks = hooks.clone();
+        let (mailbox, handle) = workspace
+            .spawn_supervised("lifecycle", move || LifecycleActor {
+                hooks: factory_hooks.clone(),
+            })
+            .unwrap();
+
+        mailbox.send(Message::Crash).await.unwrap();
+        mailbox.send(Message::Quit).await.unwrap();
+        handle.await.unwrap();
+
+        assert_eq!(
+            vec![
+                "started",
+                "Crash",
+                "stopped: failed: handler error: crash",
+                "started",
+                "Quit",
+                "stopped: quit",
+            ],
+            *hooks.lock().unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn lookup_registered_actor_by_name() {
+        let workspace = Workspace::default();
+        let (_counter, _) = workspace.spawn("counter", CounterActor::default()).unwrap();
+
+        let mailbox = workspace.lookup::<CounterActor>("counter").unwrap();
+        mailbox.send(Message::Increment).await.unwrap();
+
+        assert_eq!(1, counter(&mailbox).await);
+        assert!(workspace.lookup::<CounterActor>("unknown").is_none());
+        assert!(workspace.lookup::<LifecycleActor>("counter").is_none());
+    }
+
+    #[tokio::test]
+    async fn stop_and_deregister_once_every_mailbox_is_dropped() {
+        let hooks = Arc::new(Mutex::new(Vec::new()));
+        let workspace = Workspace::default();
+        let (mailbox, handle) = workspace
+            .spawn(
+                "lifecycle",
+                LifecycleActor {
+                    hooks: hooks.clone(),
+                },
+            )
+            .unwrap();
+        let looked_up = workspace.lookup::<LifecycleActor>("lifecycle").unwrap();
+
+        drop(mailbox);
+        drop(looked_up);
+        handle.await.unwrap();
+
+        assert_eq!(
+            Some("stopped: mailbox closed"),
+            hooks.lock().unwrap().last().map(String::as_str)
+        );
+        assert!(workspace.lookup::<LifecycleActor>("lifecycle").is_none());
+        assert!(workspace.actors().is_empty());
+    }
+
+    #[tokio::test]
+    async fn reject_duplicate_names() {
+        let workspace = Workspace::default();
+        let (_counter, _) = workspace.spawn("counter", CounterActor::default()).unwrap();
+
+        assert!(matches!(
+            workspace.spawn("counter", CounterActor::default()),
+            Err(ActorError::AlreadyRegistered(name)) if name == "counter"
+        ));
+    }
+
+    #[tokio::test]
+    async fn list_live_actors_and_deregister_on_exit() {
+        let workspace = Workspace::default();
+        let (first, first_handle) = workspace.spawn("first", CounterActor::default()).unwrap();
+        let (_second, _) = workspace.spawn("second", CounterActor::default()).unwrap();
+
+        let actors = workspace.actors();
+        assert_eq!(
+            vec![
+                ("first", ActorStatus::Running),
+                ("second", ActorStatus::Running)
+            ],
+            actors
+                .iter()
+                .map(|actor| (actor.name.as_str(), actor.status))
+                .collect::<Vec<_>>()
+        );
+
+        first.send(Message::Quit).await.unwrap();
+        first_handle.await.unwrap();
+
+        let names: Vec<String> = workspace.actors().into_iter().map(|a| a.name).collect();
+        assert_eq!(vec!["second"], names);
+    }
+
+    #[tokio::test]
+    async fn shutdown_drains_inboxes_and_runs_stop_hooks() {
+        let hooks = Arc::new(Mutex::new(Vec::new()));
+        let workspace = Workspace::default();
+        let (mailbox, handle) = workspace
+            .spawn(
+                "lifecycle",
+                LifecycleActor {
+                    hooks: hooks.clone(),
+                },
+            )
+            .unwrap();
+
+        mailbox.send(Message::Increment).await.unwrap();
+        mailbox.send(Message::Decrement).await.unwrap();
+
+        let report = workspace.shutdown(Duration::from_secs(1)).await;
+        handle.await.unwrap();
+
+        assert!(report.is_complete());
+        assert!(workspace.actors().is_empty());
+        assert!(mailbox.send(Message::Increment).await.is_err());
+        assert_eq!(
+            vec![
+                "started",
+                "Increment",
+                "Decrement",
+                "stopped: workspace shutdown"
+            ],
+            *hooks.lock().unwrap()
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn shutdown_reports_actors_that_did_not_finish() {
+        let workspace = Workspace::default();
+        let (hanging, _) = workspace.spawn("hanging", CounterActor::default()).unwrap();
+        let (_, _) = workspace
+            .spawn_supervised("counter", CounterActor::default)
+            .unwrap();
+
+        hanging.send(Message::Hang).await.unwrap();
+
+        let report = workspace.shutdown(Duration::from_secs(5)).await;
+
+        assert_eq!(
+            vec!["hanging"],
+            report
+                .unfinished
+                .iter()
+                .map(|actor| actor.name.as_str())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn actor_spawned_after_shutdown_stops_immediately() {
+        let workspace = Workspace::default();
+        workspace.shutdown(Duration::from_secs(1)).await;
+
+        let (_, handle) = workspace.spawn("late", CounterActor::default()).unwrap();
+        handle.await.unwrap();
+
+        assert!(workspace.actors().is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn snapshot_actor_metrics() {
+        let workspace = Workspace::default();
+        let (mailbox, _) = workspace
+            .spawn_supervised("counter", CounterActor::default)
+            .unwrap();
+        let mut events = workspace.events();
+
+        mailbox.send(Message::Increment).await.unwrap();
+        mailbox.send(Message::Crash).await.unwrap();
+        wait_for_restarts(&mut events, 1).await;
+        assert_eq!(0, counter(&mailbox).await);
+
+        let metrics = workspace.metrics();
+        assert_eq!(1, metrics.len());
+
+        let metrics = &metrics[0];
+        assert_eq!("counter", metrics.name);
+        assert_eq!(3, metrics.messages);
+        assert_eq!(1, metrics.errors);
+        assert_eq!(1, metrics.restarts);
+        assert_eq!(metrics::QueueDepth::default(), metrics.queue);
+    }
+
+    #[tokio::test]
+    async fn report_queue_depth_per_priority() {
+        let workspace = Workspace::default();
+        let (mailbox, _) = workspace.spawn("counter", CounterActor::default()).unwrap();
+
+        mailbox.send(Message::Hang).await.unwrap();
+        tokio::task::yield_now().await;
+        mailbox.send(Message::Increment).await.unwrap();
+        mailbox.send(Message::Increment).await.unwrap();
+
+        assert_eq!(2, workspace.metrics()[0].queue.normal);
+        assert_eq!(0, workspace.metrics()[0].queue.high);
+    }
+
+    #[derive(Debug)]
+    enum Relay {
+        Forward,
+        Peer,
+        SpanName,
+    }
+
+    /// Sends `Relay::Peer` to its peer, right away and after a delay.
+    struct RelayActor {
+        peer: Mailbox<RelayActor>,
+    }
+
+    #[async_trait]
+    impl Actor for RelayActor {
+        type Message = Relay;
+
+        async fn handle(&mut self, ctx: &Context<Self>, message: Relay) -> Result<(), ActorError> {
+            match message {
+                Relay::Forward => {
+                    self.peer.try_send(Relay::Peer)?;
+                    let peer = self.peer.clone();
+                    tokio::spawn(mailbox::propagate(async move {
+                        tokio::time::sleep(Duration::from_secs(1)).await;
+                        let _ = peer.send(Relay::Peer).await;
+                    }));
+                }
+                Relay::Peer => {}
+                Relay::SpanName => {
+                    ctx.reply(tracing::Span::current().metadata().map(|span| span.name()))
+                }
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn propagate_correlation_id_to_messages_sent_by_handler() {
+        let testkit = testkit::TestKit::default();
+        let (peer, mut probe) = testkit.probe("peer");
+        let mut relay = testkit.spawn("relay", RelayActor { peer }).await.unwrap();
+        let correlation_id = Uuid::new_v4();
+
+        relay
+            .mailbox()
+            .try_send_with_envelop(
+                Envelop::normal(Relay::Forward).with_correlation_id(correlation_id),
+            )
+            .unwrap();
+        relay.run_until_idle().await.unwrap();
+        testkit.advance(Duration::from_secs(1)).await;
+
+        for _ in 0..2 {
+            let envelop = probe.try_recv_envelop().unwrap();
+            assert_eq!(correlation_id, envelop.correlation_id());
+        }
+        probe.expect_none();
+    }
+
+    #[tokio::test]
+    async fn new_messages_get_their_own_correlation_id() {
+        assert_ne!(
+            Envelop::normal(Relay::Peer).correlation_id(),
+            Envelop::normal(Relay::Peer).correlation_id()
+        );
+        assert_eq!(None, current_correlation_id());
+    }
+
+    #[tokio::test]
+    async fn handle_runs_in_its_own_span() {
+        let _subscriber = tracing::subscriber::set_default(tracing_subscriber::registry());
+        let testkit = testkit::TestKit::default();
+        let (peer, _probe) = testkit.probe("peer");
+        let mut relay = testkit.spawn("relay", RelayActor { peer }).await.unwrap();
+
+        let span: Option<&'static str> = relay.ask(Relay::SpanName).await.unwrap();
+
+        assert_eq!(Some("handle"), span);
+    }
+
+    #[tokio::test]
+    async fn report_handler_panic_as_failure() {
+        let workspace = Workspace::default();
+        let mut events = workspace.events();
+        let (mailbox, handle) = workspace.spawn("counter", CounterActor::default()).unwrap();
+
+        mailbox.send(Message::Panic).await.unwrap();
+
+        assert!(handle.await.is_ok());
+        match events.recv().await.unwrap() {
+            SupervisorEvent::Failed {
+                error:
+                    ActorError::Panicked {
+                        actor,
+                        message,
+                        panic,
+                    },
+                ..
+            } => {
+                assert_eq!("counter", actor);
+                assert_eq!("Panic", message);
+                assert_eq!("boom", panic);
+            }
+            event => panic!("unexpected event {:?}", event),
+        }
+        assert!(mailbox.send(Message::Increment).await.is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn restart_supervised_actor_after_panic() {
+        let workspace = workspace(Strategy::OneForOne);
+        let mut events = workspace.events();
+        let (mailbox, _) = workspace
+            .spawn_supervised("counter", CounterActor::default)
+            .unwrap();
+
+        mailbox.send(Message::Increment).await.unwrap();
+        mailbox.send(Message::Panic).await.unwrap();
+        wait_for_restarts(&mut events, 1).await;
+
+        assert_eq!(0, counter(&mailbox).await);
+        assert_eq!(1, workspace.metrics()[0].errors);
+    }
+
+    /// Counter resetting its state when it panics.
+    #[derive(Debug, Default)]
+    struct FragileActor(CounterActor);
+
+    #[async_trait]
+    impl Actor for FragileActor {
+        type Message = Message;
+
+        fn panic_policy() -> PanicPolicy {
+            PanicPolicy::Reset
+        }
+
+        async fn handle(
+            &mut self,
+            ctx: &Context<Self>,
+            message: Message,
+        ) -> Result<(), ActorError> {
+            match message {
+                Message::Increment => self.0.count += 1,
+                Message::Count => ctx.reply(self.0.count),
+                Message::Panic => panic!("boom"),
+                _ => {}
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reset_state_after_panic() {
+        let workspace = workspace(Strategy::OneForOne);
+        let (mailbox, _) = workspace
+            .spawn_supervised("fragile", FragileActor::default)
+            .unwrap();
+
+        for _ in 0..2 {
+            mailbox.send(Message::Increment).await.unwrap();
+            mailbox.send(Message::Panic).await.unwrap();
+        }
+        mailbox.send(Message::Increment).await.unwrap();
+
+        let count: isize = mailbox
+            .ask(Message::Count, Duration::from_secs(1))
+            .await
+            .unwrap();
+        assert_eq!(1, count);
+        assert_eq!(2, workspace.metrics()[0].restarts);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reset_counts_against_restart_intensity() {
+        let workspace = workspace(Strategy::OneForOne);
+        let mut events = workspace.events();
+        let (mailbox, handle) = workspace
+            .spawn_supervised("fragile", FragileActor::default)
+            .unwrap();
+
+        for _ in 0..4 {
+            mailbox.send(Message::Panic).await.unwrap();
+        }
+        handle.await.unwrap();
+
+        let mut gave_up = false;
+        while let Ok(event) = events.try_recv() {
+            gave_up |= matches!(event, SupervisorEvent::GaveUp { .. });
+        }
+        assert!(gave_up);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reset_leaves_siblings_running() {
+        let workspace = workspace(Strategy::OneForAll);
+        let mut events = workspace.events();
+        let (sibling, _) = workspace
+            .spawn_supervised("counter", CounterActor::default)
+            .unwrap();
+        let (fragile, _) = workspace
+            .spawn_supervised("fragile", FragileActor::default)
+            .unwrap();
+
+        sibling.send(Message::Increment).await.unwrap();
+        fragile.send(Message::Panic).await.unwrap();
+        wait_for_restarts(&mut events, 1).await;
+
+        assert_eq!(1, counter(&sibling).await);
+    }
+
+    /// Panics in the lifecycle hook named by `panic_in`.
+    #[derive(Debug)]
+    struct HookPanicActor {
+        panic_in: &'static str,
+    }
+
+    #[async_trait]
+    impl Actor for HookPanicActor {
+        type Message = Message;
+
+        async fn started(&mut self, _ctx: &Context<Self>) -> Result<(), ActorError> {
+            if self.panic_in == "started" {
+                panic!("boom");
+            }
+            Ok(())
+        }
+
+        async fn handle(
+            &mut self,
+            _ctx: &Context<Self>,
+            message: Self::Message,
+        ) -> Result<(), ActorError> {
+            match message {
+                Message::Quit => Err(ActorError::Quit),
+                _ => Ok(()),
+            }
+        }
+
+        async fn stopped(&mut self, _ctx: &Context<Self>, _reason: &StopReason) {
+            if self.panic_in == "stopped" {
+                panic!("boom");
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn report_started_panic_as_failure() {
+        let workspace = Workspace::default();
+        let mut events = workspace.events();
+        let (_mailbox, handle) = workspace
+            .spawn(
+                "hook",
+                HookPanicActor {
+                    panic_in: "started",
+                },
+            )
+            .unwrap();
+
+        assert!(handle.await.is_ok());
+        match events.recv().await.unwrap() {
+            SupervisorEvent::Failed {
+                error: ActorError::Panicked { message, panic, .. },
+                ..
+            } => {
+                assert_eq!("started", message);
+                assert_eq!("boom", panic);
+            }
+            event => panic!("unexpected event {:?}", event),
+        }
+    }
+
+    #[tokio::test]
+    async fn deregister_actor_panicking_in_stopped() {
+        let workspace = Workspace::default();
+        let (mailbox, handle) = workspace
+            .spawn(
+                "hook",
+                HookPanicActor {
+                    panic_in: "stopped",
+                },
+            )
+            .unwrap();
+
+        mailbox.send(Message::Quit).await.unwrap();
+
+        assert!(handle.await.is_ok());
+        assert!(workspace.actors().is_empty());
+    }
 }
@@ -0,0 +1,54 @@
+use std::{
+    any::Any,
+    future::Future,
+    panic::{catch_unwind, AssertUnwindSafe},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// What happens to an actor whose handler panics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PanicPolicy {
+    /// The panic fails the actor like an error returned by the handler, the
+    /// supervisor decides whether it restarts.
+    #[default]
+    Fail,
+    /// A supervised actor is restarted alone with a fresh instance built by
+    /// its factory, its siblings keep running whatever the strategy. The
+    /// restart still waits for the backoff and counts against the intensity.
+    Reset,
+}
+
+/// Future resolving to `Err` with the panic payload when `F` panics.
+pub(super) struct CatchUnwind<F>(F);
+
+impl<F: Future + Unpin> CatchUnwind<F> {
+    pub fn new(future: F) -> Self {
+        CatchUnwind(future)
+    }
+}
+
+impl<F: Future + Unpin> Future for CatchUnwind<F> {
+    type Output = Result<F::Output, Box<dyn Any + Send>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let future = &mut self.0;
+
+        match catch_unwind(AssertUnwindSafe(|| Pin::new(future).poll(cx))) {
+            Ok(Poll::Pending) => Poll::Pending,
+            Ok(Poll::Ready(output)) => Poll::Ready(Ok(output)),
+            Err(payload) => Poll::Ready(Err(payload)),
+        }
+    }
+}
+
+/// Text given to `panic!`, when there is one.
+pub(super) fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "non-string panic payload".to_string()
+    }
+}
@@ -0,0 +1,405 @@
+//! Event-sourced actors.
+//!
+//! A `PersistentActor` changes its state only through events. `persist`
+//! appends an event to the journal before applying it, and the state is
+//! rebuilt from the latest snapshot plus the events that follow it each time
+//! the actor starts, including after a restart. The actor is spawned wrapped
+//! in `Persistent`, its registered name is its persistence id.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::{de::DeserializeOwned, Serialize};
+use tracing::{debug, warn};
+
+use super::{
+    journal::{Journal, JournalEntry, Snapshot},
+    mailbox::MailboxOptions,
+    panic::PanicPolicy,
+    stash::StashOptions,
+    Actor, ActorError, Context, StopReason,
+};
+
+const DEFAULT_SNAPSHOT_INTERVAL: u64 = 100;
+
+pub type PersistentContext<P> = Context<Persistent<P>>;
+
+#[async_trait]
+pub trait PersistentActor: Send + Sized + 'static {
+    type Message: std::fmt::Debug + Send + Sync + 'static;
+    type Event: Serialize + DeserializeOwned + std::fmt::Debug + Send + Sync;
+    type State: Serialize + DeserializeOwned + Default + Send + Sync;
+
+    fn mailbox_options() -> MailboxOptions {
+        MailboxOptions::default()
+    }
+
+    fn stash_options() -> StashOptions {
+        StashOptions::default()
+    }
+
+    fn panic_policy() -> PanicPolicy {
+        PanicPolicy::default()
+    }
+
+    /// Number of events persisted between two snapshots, `None` keeps every
+    /// event and never snapshots.
+    fn snapshot_interval() -> Option<u64> {
+        Some(DEFAULT_SNAPSHOT_INTERVAL)
+    }
+
+    /// Folds `event` into `state`, both when it is persisted and when it is
+    /// replayed.
+    fn apply(state: &mut Self::State, event: &Self::Event);
+
+    /// Called once the state is rebuilt from the journal, before the first
+    /// message is handled.
+    async fn recovered(
+        &mut self,
+        _ctx: &PersistentContext<Self>,
+        _state: &Self::State,
+    ) -> Result<(), ActorError> {
+        Ok(())
+    }
+
+    async fn handle(
+        &mut self,
+        ctx: &PersistentContext<Self>,
+        persisted: &mut Persisted<Self>,
+        message: Self::Message,
+    ) -> Result<(), ActorError>;
+
+    async fn stopped(
+        &mut self,
+        _ctx: &PersistentContext<Self>,
+        _state: &Self::State,
+        _reason: &StopReason,
+    ) {
+    }
+}
+
+/// State of a persistent actor and the journal it is recorded in.
+pub struct Persisted<P: PersistentActor> {
+    id: String,
+    journal: Arc<dyn Journal>,
+    state: P::State,
+    sequence: u64,
+    since_snapshot: u64,
+}
+
+impl<P: PersistentActor> Persisted<P> {
+    pub fn state(&self) -> &P::State {
+        &self.state
+    }
+
+    /// Sequence of the last event applied to the state.
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    /// Appends `event` to the journal and applies it once it is durable. The
+    /// state is left untouched when the journal fails.
+    pub async fn persist(&mut self, event: P::Event) -> Result<(), ActorError> {
+        let entry = JournalEntry {
+            sequence: self.sequence + 1,
+            event: serde_json::to_value(&event).map_err(journal_error)?,
+        };
+        self.journal.append(&self.id, entry).await?;
+
+        P::apply(&mut self.state, &event);
+        self.sequence += 1;
+        self.since_snapshot += 1;
+
+        if P::snapshot_interval().is_some_and(|interval| self.since_snapshot >= interval) {
+            self.snapshot().await;
+        }
+        Ok(())
+    }
+
+    /// A failed snapshot only means a longer replay, the events are durable.
+    async fn snapshot(&mut self) {
+        let snapshot = match serde_json::to_value(&self.state) {
+            Ok(state) => Snapshot {
+                sequence: self.sequence,
+                state,
+            },
+            Err(e) => {
+                warn!("can't serialize the state of `{}`: {}", self.id, e);
+                return;
+            }
+        };
+
+        match self.journal.save_snapshot(&self.id, snapshot).await {
+            Ok(()) => self.since_snapshot = 0,
+            Err(e) => warn!("can't snapshot `{}`: {}", self.id, e),
+        }
+    }
+
+    async fn recover(&mut self, id: &str) -> Result<(), ActorError> {
+        self.id = id.to_string();
+        self.state = P::State::default();
+        self.sequence = 0;
+        self.since_snapshot = 0;
+
+        if let Some(snapshot) = self.journal.load_snapshot(id).await? {
+            self.state = serde_json::from_value(snapshot.state).map_err(journal_error)?;
+            self.sequence = snapshot.sequence;
+        }
+
+        for entry in self.journal.replay(id, self.sequence).await? {
+            if entry.sequence != self.sequence + 1 {
+                return Err(ActorError::Journal(format!(
+                    "`{}` expected event {} but found {}",
+                    id,
+                    self.sequence + 1,
+                    entry.sequence
+                )));
+            }
+
+            let event: P::Event = serde_json::from_value(entry.event).map_err(journal_error)?;
+            P::apply(&mut self.state, &event);
+            self.sequence = entry.sequence;
+            self.since_snapshot += 1;
+        }
+
+        debug!("recovered `{}` at event {}", id, self.sequence);
+        Ok(())
+    }
+}
+
+/// Adapts a `PersistentActor` to `Actor`, give it to `Workspace::spawn` or
+/// build it in the factory of `Workspace::spawn_supervised`.
+pub struct Persistent<P: PersistentActor> {
+    actor: P,
+    persisted: Persisted<P>,
+}
+
+impl<P: PersistentActor> Persistent<P> {
+    pub fn new(actor: P, journal: Arc<dyn Journal>) -> Self {
+        Persistent {
+            actor,
+            persisted: Persisted {
+                id: String::new(),
+                journal,
+                state: P::State::default(),
+                sequence: 0,
+                since_snapshot: 0,
+            },
+        }
+    }
+
+    pub fn actor(&self) -> &P {
+        &self.actor
+    }
+
+    pub fn state(&self) -> &P::State {
+        self.persisted.state()
+    }
+}
+
+#[async_trait]
+impl<P: PersistentActor> Actor for Persistent<P> {
+    type Message = P::Message;
+
+    fn mailbox_options() -> MailboxOptions {
+        P::mailbox_options()
+    }
+
+    fn stash_options() -> StashOptions {
+        P::stash_options()
+    }
+
+    fn panic_policy() -> PanicPolicy {
+        P::panic_policy()
+    }
+
+    async fn started(&mut self, ctx: &Context<Self>) -> Result<(), ActorError> {
+        self.persisted.recover(ctx.name()).await?;
+        self.actor.recovered(ctx, &self.persisted.state).await
+    }
+
+    async fn handle(
+        &mut self,
+        ctx: &Context<Self>,
+        message: Self::Message,
+    ) -> Result<(), ActorError> {
+        self.actor.handle(ctx, &mut self.persisted, message).await
+    }
+
+    async fn stopped(&mut self, ctx: &Context<Self>, reason: &StopReason) {
+        self.actor.stopped(ctx, &self.persisted.state, reason).await;
+    }
+}
+
+fn journal_error(error: serde_json::Error) -> ActorError {
+    ActorError::Journal(error.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use serde::Deserialize;
+
+    use crate::actor::{
+        journal::{FileJournal, MemoryJournal},
+        mailbox::Mailbox,
+        supervisor::SupervisorEvent,
+        testkit::Scratch,
+        Workspace,
+    };
+
+    use super::*;
+
+    #[derive(Debug)]
+    enum Message {
+        AttachDisk(u32),
+        Disks,
+        Crash,
+        Quit,
+    }
+
+    #[derive(Debug, Serialize, Deserialize)]
+    enum Event {
+        DiskAttached(u32),
+    }
+
+    #[derive(Debug, Default, Serialize, Deserialize)]
+    struct Vm {
+        disks: Vec<u32>,
+    }
+
+    struct VmActor;
+
+    #[async_trait]
+    impl PersistentActor for VmActor {
+        type Message = Message;
+        type Event = Event;
+        type State = Vm;
+
+        fn snapshot_interval() -> Option<u64> {
+            Some(2)
+        }
+
+        fn apply(state: &mut Vm, event: &Event) {
+            match event {
+                Event::DiskAttached(disk) => state.disks.push(*disk),
+            }
+        }
+
+        async fn handle(
+            &mut self,
+            ctx: &PersistentContext<Self>,
+            persisted: &mut Persisted<Self>,
+            message: Message,
+        ) -> Result<(), ActorError> {
+            match message {
+                Message::AttachDisk(disk) => persisted.persist(Event::DiskAttached(disk)).await?,
+                Message::Disks => ctx.reply(persisted.state().disks.clone()),
+                Message::Crash => return Err(ActorError::Handler("crash".to_string())),
+                Message::Quit => return Err(ActorError::Quit),
+            }
+            Ok(())
+        }
+    }
+
+    async fn disks(mailbox: &Mailbox<Persistent<VmActor>>) -> Vec<u32> {
+        mailbox
+            .ask(Message::Disks, Duration::from_secs(1))
+            .await
+            .unwrap()
+    }
+
+    async fn attach(workspace: &Workspace, journal: Arc<dyn Journal>, disks: &[u32]) {
+        let (mailbox, handle) = workspace
+            .spawn("vm", Persistent::new(VmActor, journal))
+            .unwrap();
+        for disk in disks {
+            mailbox.send(Message::AttachDisk(*disk)).await.unwrap();
+        }
+        mailbox.send(Message::Quit).await.unwrap();
+        handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn rebuild_state_from_snapshot_and_events() {
+        let workspace = Workspace::default();
+        let journal = MemoryJournal::default();
+        attach(&workspace, Arc::new(journal.clone()), &[1, 2, 3]).await;
+
+        assert_eq!(
+            2,
+            journal.load_snapshot("vm").await.unwrap().unwrap().sequence
+        );
+        assert_eq!(1, journal.replay("vm", 0).await.unwrap().len());
+
+        let (mailbox, _) = workspace
+            .spawn("vm", Persistent::new(VmActor, Arc::new(journal)))
+            .unwrap();
+        assert_eq!(vec![1, 2, 3], disks(&mailbox).await);
+    }
+
+    #[tokio::test]
+    async fn recover_after_supervised_restart() {
+        let workspace = Workspace::default();
+        let mut events = workspace.events();
+        let journal = MemoryJournal::default();
+        let (mailbox, _) = workspace
+            .spawn_supervised("vm", move || {
+                Persistent::new(VmActor, Arc::new(journal.clone()))
+            })
+            .unwrap();
+
+        mailbox.send(Message::AttachDisk(1)).await.unwrap();
+        mailbox.send(Message::Crash).await.unwrap();
+        while !matches!(
+            events.recv().await.unwrap(),
+            SupervisorEvent::Restarted { .. }
+        ) {}
+
+        assert_eq!(vec![1], disks(&mailbox).await);
+    }
+
+    #[tokio::test]
+    async fn recover_from_file_journal() {
+        let scratch = Scratch::new("vm");
+        let journal = FileJournal::open(scratch.path()).await.unwrap();
+        let workspace = Workspace::default();
+        attach(&workspace, Arc::new(journal), &[1, 2, 3]).await;
+
+        let journal = FileJournal::open(scratch.path()).await.unwrap();
+        let (mailbox, _) = workspace
+            .spawn("vm", Persistent::new(VmActor, Arc::new(journal)))
+            .unwrap();
+        assert_eq!(vec![1, 2, 3], disks(&mailbox).await);
+    }
+
+    #[tokio::test]
+    async fn refuse_to_start_with_missing_events() {
+        let workspace = Workspace::default();
+        let journal = MemoryJournal::default();
+        journal
+            .append(
+                "vm",
+                JournalEntry {
+                    sequence: 2,
+                    event: serde_json::to_value(Event::DiskAttached(2)).unwrap(),
+                },
+            )
+            .await
+            .unwrap();
+        let mut events = workspace.events();
+
+        workspace
+            .spawn("vm", Persistent::new(VmActor, Arc::new(journal)))
+            .unwrap();
+
+        assert!(matches!(
+            events.recv().await.unwrap(),
+            SupervisorEvent::Failed {
+                error: ActorError::Journal(_),
+                ..
+            }
+        ));
+    }
+}
@@ -0,0 +1,177 @@
+use std::{
+    any::Any,
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::{
+    mailbox::{Mailbox, WeakMailbox},
+    metrics::{ActorMetrics, ActorMetricsSnapshot, QueueDepth},
+    Actor, ActorError,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ActorStatus {
+    Running,
+    Restarting,
+}
+
+impl std::fmt::Display for ActorStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ActorStatus::Running => write!(f, "running"),
+            ActorStatus::Restarting => write!(f, "restarting"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActorInfo {
+    pub name: String,
+    pub id: Uuid,
+    pub status: ActorStatus,
+}
+
+/// Type erased mailbox kept by the registry. It is weak, the registry does
+/// not keep the inbox of an actor open.
+trait Registered: Send + Sync {
+    fn depth(&self) -> QueueDepth;
+    fn expired(&self) -> u64;
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<A: Actor + 'static> Registered for WeakMailbox<A> {
+    fn depth(&self) -> QueueDepth {
+        WeakMailbox::depth(self)
+    }
+
+    fn expired(&self) -> u64 {
+        WeakMailbox::expired(self)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+struct Entry {
+    id: Uuid,
+    status: ActorStatus,
+    mailbox: Box<dyn Registered>,
+    metrics: Arc<ActorMetrics>,
+}
+
+impl std::fmt::Debug for Entry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Entry")
+            .field("id", &self.id)
+            .field("status", &self.status)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Live actors of a workspace indexed by name.
+#[derive(Debug, Clone, Default)]
+pub(super) struct Registry {
+    entries: Arc<Mutex<BTreeMap<String, Entry>>>,
+}
+
+impl Registry {
+    pub fn register<A: Actor + 'static>(
+        &self,
+        name: &str,
+        id: Uuid,
+        mailbox: &Mailbox<A>,
+        metrics: Arc<ActorMetrics>,
+    ) -> Result<(), ActorError> {
+        let mut entries = self.entries.lock().unwrap();
+
+        if entries.contains_key(name) {
+            return Err(ActorError::AlreadyRegistered(name.to_string()));
+        }
+
+        entries.insert(
+            name.to_string(),
+            Entry {
+                id,
+                status: ActorStatus::Running,
+                mailbox: Box::new(mailbox.downgrade()),
+                metrics,
+            },
+        );
+
+        Ok(())
+    }
+
+    /// Removes `name` only if it is still registered by the actor `id`.
+    pub fn deregister(&self, name: &str, id: Uuid) {
+        let mut entries = self.entries.lock().unwrap();
+
+        if entries.get(name).is_some_and(|entry| entry.id == id) {
+            entries.remove(name);
+        }
+    }
+
+    pub fn set_status(&self, name: &str, status: ActorStatus) {
+        if let Some(entry) = self.entries.lock().unwrap().get_mut(name) {
+            entry.status = status;
+        }
+    }
+
+    pub fn id(&self, name: &str) -> Option<Uuid> {
+        self.entries.lock().unwrap().get(name).map(|entry| entry.id)
+    }
+
+    pub fn contains(&self, id: Uuid) -> bool {
+        self.entries
+            .lock()
+            .unwrap()
+            .values()
+            .any(|entry| entry.id == id)
+    }
+
+    /// `None` once every mailbox of the actor was dropped.
+    pub fn lookup<A: Actor + 'static>(&self, name: &str) -> Option<Mailbox<A>> {
+        self.entries
+            .lock()
+            .unwrap()
+            .get(name)
+            .and_then(|entry| entry.mailbox.as_any().downcast_ref::<WeakMailbox<A>>())
+            .and_then(WeakMailbox::upgrade)
+    }
+
+    pub fn actors(&self) -> Vec<ActorInfo> {
+        self.entries
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, entry)| ActorInfo {
+                name: name.clone(),
+                id: entry.id,
+                status: entry.status,
+            })
+            .collect()
+    }
+
+    pub fn metrics(&self) -> Vec<ActorMetricsSnapshot> {
+        self.entries
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, entry)| {
+                entry.metrics.snapshot(
+                    name,
+                    entry.id,
+                    entry.status,
+                    entry.mailbox.depth(),
+                    entry.mailbox.expired(),
+                )
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,217 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use super::mailbox::Envelop;
+
+const DEFAULT_STASH_CAPACITY: usize = 64;
+
+/// What `Context::stash` does with a message once the stash is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StashOverflow {
+    /// Refuse the message, `stash` fails with `ActorError::StashFull`.
+    #[default]
+    Reject,
+    /// Discard the oldest stashed message to make room.
+    DropOldest,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct StashOptions {
+    /// Number of messages an actor can defer.
+    pub capacity: usize,
+    pub overflow: StashOverflow,
+}
+
+impl Default for StashOptions {
+    fn default() -> Self {
+        StashOptions {
+            capacity: DEFAULT_STASH_CAPACITY,
+            overflow: StashOverflow::default(),
+        }
+    }
+}
+
+/// Messages deferred by an actor until it changes behavior.
+#[derive(Debug)]
+pub(super) struct Stash<M: std::fmt::Debug> {
+    options: StashOptions,
+    stashed: Arc<Mutex<VecDeque<Envelop<M>>>>,
+}
+
+impl<M: std::fmt::Debug> Clone for Stash<M> {
+    fn clone(&self) -> Self {
+        Stash {
+            options: self.options,
+            stashed: self.stashed.clone(),
+        }
+    }
+}
+
+impl<M: std::fmt::Debug> Stash<M> {
+    pub fn new(options: StashOptions) -> Self {
+        Stash {
+            options,
+            stashed: Arc::new(Mutex::new(VecDeque::new())),
+        }
+    }
+
+    /// Stashes `envelop` and returns the message the overflow policy evicted,
+    /// or gives `envelop` back when it is refused.
+    pub fn push(&self, envelop: Envelop<M>) -> Result<Option<Envelop<M>>, Envelop<M>> {
+        let mut stashed = self.stashed.lock().unwrap();
+        let mut evicted = None;
+
+        if stashed.len() >= self.options.capacity {
+            match self.options.overflow {
+                StashOverflow::Reject => return Err(envelop),
+                StashOverflow::DropOldest => evicted = stashed.pop_front(),
+            }
+        }
+
+        stashed.push_back(envelop);
+        Ok(evicted)
+    }
+
+    pub fn len(&self) -> usize {
+        self.stashed.lock().unwrap().len()
+    }
+
+    /// Removes every stashed message, oldest first.
+    pub fn drain(&self) -> Vec<Envelop<M>> {
+        self.stashed.lock().unwrap().drain(..).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use async_trait::async_trait;
+
+    use crate::actor::{dead_letter::DeadLetterReason, Actor, ActorError, Context, Workspace};
+
+    use super::*;
+
+    #[derive(Debug)]
+    enum Message {
+        Boot,
+        AttachDisk(u32),
+    }
+
+    #[derive(Debug, Default)]
+    struct VmActor {
+        running: bool,
+        disks: Vec<u32>,
+    }
+
+    #[async_trait]
+    impl Actor for VmActor {
+        type Message = Message;
+
+        fn stash_options() -> StashOptions {
+            StashOptions {
+                capacity: 2,
+                ..Default::default()
+            }
+        }
+
+        async fn handle(
+            &mut self,
+            ctx: &Context<Self>,
+            message: Self::Message,
+        ) -> Result<(), ActorError> {
+            match message {
+                Message::Boot => {
+                    self.running = true;
+                    ctx.unstash_all();
+                }
+                message @ Message::AttachDisk(_) if !self.running => {
+                    let _ = ctx.stash(message);
+                }
+                Message::AttachDisk(disk) => {
+                    self.disks.push(disk);
+                    ctx.reply(self.disks.clone());
+                }
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn replay_stashed_messages_in_order_after_boot() {
+        let workspace = Workspace::default();
+        let (mailbox, _) = workspace.spawn("vm", VmActor::default()).unwrap();
+
+        mailbox.send(Message::AttachDisk(1)).await.unwrap();
+        mailbox.send(Message::AttachDisk(2)).await.unwrap();
+        mailbox.send(Message::Boot).await.unwrap();
+
+        let disks: Vec<u32> = mailbox
+            .ask(Message::AttachDisk(3), Duration::from_secs(1))
+            .await
+            .unwrap();
+        assert_eq!(vec![1, 2, 3], disks);
+    }
+
+    #[tokio::test]
+    async fn answer_stashed_ask_once_replayed() {
+        let workspace = Workspace::default();
+        let (mailbox, _) = workspace.spawn("vm", VmActor::default()).unwrap();
+
+        let attach = tokio::spawn({
+            let mailbox = mailbox.clone();
+            async move {
+                mailbox
+                    .ask::<Vec<u32>>(Message::AttachDisk(1), Duration::from_secs(1))
+                    .await
+            }
+        });
+        tokio::task::yield_now().await;
+        mailbox.send(Message::Boot).await.unwrap();
+
+        assert_eq!(vec![1], attach.await.unwrap().unwrap());
+    }
+
+    #[tokio::test]
+    async fn refuse_messages_once_the_stash_is_full() {
+        let workspace = Workspace::default();
+        let (mailbox, _) = workspace.spawn("vm", VmActor::default()).unwrap();
+
+        mailbox.send(Message::AttachDisk(1)).await.unwrap();
+        mailbox.send(Message::AttachDisk(2)).await.unwrap();
+
+        assert!(matches!(
+            mailbox
+                .ask::<Vec<u32>>(Message::AttachDisk(3), Duration::from_secs(1))
+                .await,
+            Err(ActorError::StashFull)
+        ));
+
+        let letters = workspace.dead_letters();
+        assert_eq!("AttachDisk(3)", letters[0].message);
+        assert_eq!(DeadLetterReason::Dropped, letters[0].reason);
+    }
+
+    #[test]
+    fn evict_oldest_when_configured() {
+        let stash = Stash::new(StashOptions {
+            capacity: 1,
+            overflow: StashOverflow::DropOldest,
+        });
+
+        assert!(stash.push(Envelop::normal(1)).unwrap().is_none());
+        let evicted = stash.push(Envelop::normal(2)).unwrap().unwrap();
+
+        assert_eq!(1, *evicted.message());
+        assert_eq!(
+            vec![2],
+            stash
+                .drain()
+                .iter()
+                .map(|envelop| *envelop.message())
+                .collect::<Vec<_>>()
+        );
+    }
+}
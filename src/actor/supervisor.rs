@@ -0,0 +1,317 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::{
+    sync::{broadcast, mpsc, Notify},
+    time::Instant,
+};
+use uuid::Uuid;
+
+use super::ActorError;
+
+const SUPERVISOR_EVENTS_CAPACITY: usize = 64;
+
+/// Which actors are restarted when a supervised actor fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Strategy {
+    /// Only the failed actor is restarted.
+    #[default]
+    OneForOne,
+    /// Every supervised actor of the workspace is restarted.
+    OneForAll,
+    /// The failed actor and every actor spawned after it are restarted.
+    RestForOne,
+}
+
+/// Maximum number of restarts allowed in a sliding window, once exceeded the
+/// supervisor gives up on the failing actor.
+#[derive(Debug, Clone, Copy)]
+pub struct Intensity {
+    pub max_restarts: usize,
+    pub within: Duration,
+}
+
+impl Default for Intensity {
+    fn default() -> Self {
+        Intensity {
+            max_restarts: 3,
+            within: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Exponential delay applied before restarting a failed actor.
+#[derive(Debug, Clone, Copy)]
+pub struct Backoff {
+    pub initial: Duration,
+    pub max: Duration,
+}
+
+impl Backoff {
+    pub fn delay(&self, restarts: usize) -> Duration {
+        let exponent = restarts.saturating_sub(1).min(u32::MAX as usize) as u32;
+        let factor = 2u32.saturating_pow(exponent);
+
+        self.initial.saturating_mul(factor).min(self.max)
+    }
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Backoff {
+            initial: Duration::from_millis(100),
+            max: Duration::from_secs(5),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SupervisorOptions {
+    pub strategy: Strategy,
+    pub intensity: Intensity,
+    pub backoff: Backoff,
+}
+
+#[derive(Debug, Clone)]
+pub enum SupervisorEvent {
+    Failed { id: Uuid, error: ActorError },
+    Restarted { id: Uuid },
+    GaveUp { id: Uuid },
+    Stopped { id: Uuid },
+}
+
+#[derive(Debug)]
+pub(super) enum Control {
+    Restart,
+    Shutdown,
+}
+
+#[derive(Debug)]
+struct Child {
+    id: Uuid,
+    control: mpsc::UnboundedSender<Control>,
+    restartable: bool,
+}
+
+#[derive(Debug, Default)]
+struct Children {
+    running: Vec<Child>,
+    shutting_down: bool,
+}
+
+#[derive(Debug)]
+struct Inner {
+    options: SupervisorOptions,
+    /// Supervisor of the actor owning this scope, `None` for a workspace.
+    parent: Option<Supervisor>,
+    children: Mutex<Children>,
+    restarts: Mutex<VecDeque<Instant>>,
+    stopped: Notify,
+    events: broadcast::Sender<SupervisorEvent>,
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct Supervisor {
+    inner: Arc<Inner>,
+}
+
+impl Supervisor {
+    pub fn new(options: SupervisorOptions) -> Self {
+        let (events, _) = broadcast::channel(SUPERVISOR_EVENTS_CAPACITY);
+
+        Self::with_events(options, None, events)
+    }
+
+    /// Supervisor of the children of one actor: their restarts only affect
+    /// each other and have their own intensity, events are published with
+    /// the ones of this supervisor.
+    pub fn scope(&self) -> Self {
+        Self::with_events(
+            self.inner.options,
+            Some(self.clone()),
+            self.inner.events.clone(),
+        )
+    }
+
+    fn with_events(
+        options: SupervisorOptions,
+        parent: Option<Supervisor>,
+        events: broadcast::Sender<SupervisorEvent>,
+    ) -> Self {
+        Supervisor {
+            inner: Arc::new(Inner {
+                options,
+                parent,
+                children: Mutex::new(Children::default()),
+                restarts: Mutex::new(VecDeque::new()),
+                stopped: Notify::new(),
+                events,
+            }),
+        }
+    }
+
+    pub fn register(&self, id: Uuid, restartable: bool) -> mpsc::UnboundedReceiver<Control> {
+        let (control, rx) = mpsc::unbounded_channel();
+        let shutting_down = self.is_shutting_down();
+        let mut children = self.inner.children.lock().unwrap();
+
+        if shutting_down {
+            let _ = control.send(Control::Shutdown);
+        }
+
+        children.running.push(Child {
+            id,
+            control,
+            restartable,
+        });
+        rx
+    }
+
+    pub fn deregister(&self, id: Uuid) {
+        self.inner
+            .children
+            .lock()
+            .unwrap()
+            .running
+            .retain(|child| child.id != id);
+        self.inner.stopped.notify_waiters();
+    }
+
+    /// Asks the child `id` to drain its inbox and stop.
+    pub fn stop(&self, id: Uuid) {
+        let children = self.inner.children.lock().unwrap();
+
+        if let Some(child) = children.running.iter().find(|child| child.id == id) {
+            let _ = child.control.send(Control::Shutdown);
+        }
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        self.inner.children.lock().unwrap().shutting_down
+            || self
+                .inner
+                .parent
+                .as_ref()
+                .is_some_and(Supervisor::is_shutting_down)
+    }
+
+    /// Asks every child to drain its inbox and stop, then waits for them
+    /// until `deadline`.
+    pub async fn shutdown(&self, deadline: Instant) {
+        {
+            let mut children = self.inner.children.lock().unwrap();
+            children.shutting_down = true;
+
+            for child in children.running.iter() {
+                let _ = child.control.send(Control::Shutdown);
+            }
+        }
+
+        loop {
+            let stopped = self.inner.stopped.notified();
+            tokio::pin!(stopped);
+            stopped.as_mut().enable();
+
+            if self.inner.children.lock().unwrap().running.is_empty() {
+                return;
+            }
+
+            if tokio::time::timeout_at(deadline, stopped).await.is_err() {
+                return;
+            }
+        }
+    }
+
+    /// Records a restart and returns the number of restarts in the current
+    /// window, `None` when the intensity limit is exceeded.
+    pub fn record_restart(&self) -> Option<usize> {
+        let Intensity {
+            max_restarts,
+            within,
+        } = self.inner.options.intensity;
+        let now = Instant::now();
+        let mut restarts = self.inner.restarts.lock().unwrap();
+
+        while restarts
+            .front()
+            .is_some_and(|at| now.duration_since(*at) > within)
+        {
+            restarts.pop_front();
+        }
+
+        if restarts.len() >= max_restarts {
+            return None;
+        }
+
+        restarts.push_back(now);
+        Some(restarts.len())
+    }
+
+    pub fn backoff(&self, restarts: usize) -> Duration {
+        self.inner.options.backoff.delay(restarts)
+    }
+
+    /// Asks the siblings affected by the failure of `id` to restart.
+    pub fn restart_siblings(&self, id: Uuid) {
+        let children = self.inner.children.lock().unwrap();
+        let siblings: Box<dyn Iterator<Item = &Child>> = match self.inner.options.strategy {
+            Strategy::OneForOne => return,
+            Strategy::OneForAll => Box::new(children.running.iter()),
+            Strategy::RestForOne => {
+                Box::new(children.running.iter().skip_while(|child| child.id != id))
+            }
+        };
+
+        for sibling in siblings.filter(|child| child.id != id && child.restartable) {
+            let _ = sibling.control.send(Control::Restart);
+        }
+    }
+
+    pub fn emit(&self, event: SupervisorEvent) {
+        let _ = self.inner.events.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<SupervisorEvent> {
+        self.inner.events.subscribe()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_until_max() {
+        let backoff = Backoff {
+            initial: Duration::from_millis(100),
+            max: Duration::from_millis(500),
+        };
+
+        assert_eq!(Duration::from_millis(100), backoff.delay(1));
+        assert_eq!(Duration::from_millis(200), backoff.delay(2));
+        assert_eq!(Duration::from_millis(400), backoff.delay(3));
+        assert_eq!(Duration::from_millis(500), backoff.delay(4));
+        assert_eq!(Duration::from_millis(500), backoff.delay(64));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn restart_window_slides() {
+        let supervisor = Supervisor::new(SupervisorOptions {
+            intensity: Intensity {
+                max_restarts: 2,
+                within: Duration::from_secs(1),
+            },
+            ..Default::default()
+        });
+
+        assert_eq!(Some(1), supervisor.record_restart());
+        assert_eq!(Some(2), supervisor.record_restart());
+        assert_eq!(None, supervisor.record_restart());
+
+        tokio::time::advance(Duration::from_secs(2)).await;
+        assert_eq!(Some(1), supervisor.record_restart());
+    }
+}
@@ -0,0 +1,352 @@
+//! Deterministic harness for actor tests.
+//!
+//! Actors spawned by a `TestKit` do not run in a task, the test steps them one
+//! message at a time. Messages they send to a `Probe` mailbox are queued until
+//! the test inspects them. Tests relying on timers run on a paused clock with
+//! `#[tokio::test(start_paused = true)]` and move it with `TestKit::advance`.
+
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use uuid::Uuid;
+
+use super::{
+    bus::EventBus,
+    dead_letter::DeadLetter,
+    handle_envelop,
+    mailbox::{make_mailbox, Envelop, Inbox, Mailbox, Priority},
+    metrics::QueueDepth,
+    Actor, ActorError, Context, StopReason, Workspace,
+};
+
+const ASK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Children spawned by a stepped actor run in `workspace` like any other
+/// actor.
+#[derive(Debug, Clone, Default)]
+pub struct TestKit {
+    workspace: Workspace,
+}
+
+impl TestKit {
+    /// Runs the `started` hook of `actor` and returns it without handling any
+    /// message.
+    pub async fn spawn<A: Actor + 'static>(
+        &self,
+        name: &str,
+        mut actor: A,
+    ) -> Result<TestActor<A>, ActorError> {
+        let (mailbox, inbox) = make_mailbox();
+        mailbox.route_dead_letters(name, self.workspace.dead_letters.clone());
+        let ctx = Context::new(name, &mailbox, &self.workspace, &self.workspace.supervisor);
+
+        actor.started(&ctx).await?;
+
+        Ok(TestActor {
+            actor,
+            ctx,
+            mailbox,
+            inbox,
+        })
+    }
+
+    /// Returns a mailbox whose messages are kept for the test to inspect.
+    pub fn probe<A: Actor>(&self, name: &str) -> (Mailbox<A>, Probe<A>) {
+        let (mailbox, inbox) = make_mailbox();
+        mailbox.route_dead_letters(name, self.workspace.dead_letters.clone());
+
+        (
+            mailbox,
+            Probe {
+                name: name.to_string(),
+                inbox,
+            },
+        )
+    }
+
+    /// Moves the paused clock forward and lets the timers that elapsed
+    /// deliver their messages.
+    pub async fn advance(&self, duration: Duration) {
+        // timers spawned since the last yield must start before time moves.
+        settle().await;
+        tokio::time::advance(duration).await;
+        settle().await;
+    }
+
+    pub fn workspace(&self) -> &Workspace {
+        &self.workspace
+    }
+
+    pub fn bus(&self) -> EventBus {
+        self.workspace.bus()
+    }
+
+    pub fn dead_letters(&self) -> Vec<DeadLetter> {
+        self.workspace.dead_letters()
+    }
+}
+
+async fn settle() {
+    for _ in 0..8 {
+        tokio::task::yield_now().await;
+    }
+}
+
+/// Actor driven by the test, see `TestKit::spawn`.
+pub struct TestActor<A: Actor> {
+    actor: A,
+    ctx: Context<A>,
+    mailbox: Mailbox<A>,
+    inbox: Inbox<A>,
+}
+
+impl<A: Actor + 'static> TestActor<A> {
+    pub fn actor(&self) -> &A {
+        &self.actor
+    }
+
+    pub fn mailbox(&self) -> Mailbox<A> {
+        self.mailbox.clone()
+    }
+
+    /// Messages waiting to be stepped.
+    pub fn pending(&self) -> QueueDepth {
+        self.mailbox.depth()
+    }
+
+    pub fn send(&self, message: A::Message) -> Result<(), ActorError> {
+        self.inject(message, Priority::Normal)
+    }
+
+    /// Queues `message` at `priority`, it is handled by a later `step`.
+    pub fn inject(&self, message: A::Message, priority: Priority) -> Result<(), ActorError> {
+        self.mailbox
+            .try_send_with_envelop(Envelop::with_priority(message, priority))
+    }
+
+    /// Handles the next queued message, `None` when there is none.
+    pub async fn step(&mut self) -> Option<Result<(), ActorError>> {
+        let envelop = self.inbox.try_recv_envelop()?;
+
+        Some(handle_envelop(&mut self.actor, &self.ctx, envelop).await)
+    }
+
+    /// Steps until no message is queued and returns the number handled, stops
+    /// at the first failing message.
+    pub async fn run_until_idle(&mut self) -> Result<usize, ActorError> {
+        let mut handled = 0;
+
+        while let Some(result) = self.step().await {
+            result?;
+            handled += 1;
+        }
+
+        Ok(handled)
+    }
+
+    /// Queues `message` like `Mailbox::ask` and steps until it is answered,
+    /// the reply type is checked at runtime the same way.
+    pub async fn ask<R: Send + 'static>(&mut self, message: A::Message) -> Result<R, ActorError> {
+        let mailbox = self.mailbox.clone();
+        let (reply, handled) =
+            tokio::join!(mailbox.ask(message, ASK_TIMEOUT), self.run_until_idle());
+        handled?;
+
+        reply
+    }
+
+    /// Runs the `stopped` hook and returns the actor.
+    pub async fn stop(mut self, reason: StopReason) -> A {
+        self.ctx.children.stop_all().await;
+        self.actor.stopped(&self.ctx, &reason).await;
+        self.ctx.timers.cancel_all();
+        self.ctx.forwarders.cancel_all();
+
+        self.actor
+    }
+}
+
+/// Receiving end of a mailbox created by `TestKit::probe`.
+pub struct Probe<A: Actor> {
+    name: String,
+    inbox: Inbox<A>,
+}
+
+impl<A: Actor> Probe<A> {
+    pub fn try_recv(&mut self) -> Option<A::Message> {
+        self.try_recv_envelop()
+            .map(|envelop| envelop.into_parts().0)
+    }
+
+    pub fn try_recv_envelop(&mut self) -> Option<Envelop<A::Message>> {
+        self.inbox.try_recv_envelop()
+    }
+
+    /// Returns the next message, panics when none was sent.
+    pub fn expect(&mut self) -> A::Message {
+        match self.try_recv() {
+            Some(message) => message,
+            None => panic!("probe `{}` received no message", self.name),
+        }
+    }
+
+    /// Panics when a message is waiting in the probe.
+    pub fn expect_none(&mut self) {
+        if let Some(message) = self.try_recv() {
+            panic!("probe `{}` received {:?}", self.name, message);
+        }
+    }
+
+    /// Every message waiting in the probe, highest priority first.
+    pub fn received(&mut self) -> Vec<A::Message> {
+        std::iter::from_fn(|| self.try_recv()).collect()
+    }
+}
+
+/// Directory under the system temp dir removed when dropped, for tests
+/// writing a `FileJournal`.
+#[derive(Debug)]
+pub struct Scratch(PathBuf);
+
+impl Scratch {
+    pub fn new(prefix: &str) -> Self {
+        Scratch(std::env::temp_dir().join(format!("netheril-{}-{}", prefix, Uuid::new_v4())))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use async_trait::async_trait;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Eq)]
+    enum Message {
+        Ping(u32),
+        Echo(u32),
+        Delayed(u32, Duration),
+        Total,
+    }
+
+    /// Adds the pings it handles and forwards every ping to `peer`.
+    struct RelayActor {
+        total: u32,
+        peer: Mailbox<RelayActor>,
+    }
+
+    #[async_trait]
+    impl Actor for RelayActor {
+        type Message = Message;
+
+        async fn handle(
+            &mut self,
+            ctx: &Context<Self>,
+            message: Self::Message,
+        ) -> Result<(), ActorError> {
+            match message {
+                Message::Ping(n) => {
+                    self.total += n;
+                    self.peer.try_send(Message::Echo(n))?;
+                }
+                Message::Delayed(n, delay) => {
+                    ctx.send_after(delay, Message::Ping(n));
+                }
+                Message::Total => ctx.reply(self.total),
+                Message::Echo(_) => {}
+            }
+            Ok(())
+        }
+    }
+
+    async fn relay(testkit: &TestKit) -> (TestActor<RelayActor>, Probe<RelayActor>) {
+        let (peer, probe) = testkit.probe("peer");
+        let actor = testkit
+            .spawn("relay", RelayActor { total: 0, peer })
+            .await
+            .unwrap();
+
+        (actor, probe)
+    }
+
+    #[tokio::test]
+    async fn step_one_message_at_a_time() {
+        let testkit = TestKit::default();
+        let (mut actor, mut probe) = relay(&testkit).await;
+
+        actor.send(Message::Ping(1)).unwrap();
+        actor.send(Message::Ping(2)).unwrap();
+
+        actor.step().await.unwrap().unwrap();
+        assert_eq!(1, actor.actor().total);
+        assert_eq!(Message::Echo(1), probe.expect());
+        probe.expect_none();
+
+        actor.step().await.unwrap().unwrap();
+        assert_eq!(3, actor.actor().total);
+        assert!(actor.step().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn handle_injected_priority_first() {
+        let testkit = TestKit::default();
+        let (mut actor, mut probe) = relay(&testkit).await;
+
+        actor.inject(Message::Ping(1), Priority::Normal).unwrap();
+        actor.inject(Message::Ping(2), Priority::High).unwrap();
+
+        assert_eq!(2, actor.run_until_idle().await.unwrap());
+        assert_eq!(vec![Message::Echo(2), Message::Echo(1)], probe.received());
+    }
+
+    #[tokio::test]
+    async fn ask_steps_until_answered() {
+        let testkit = TestKit::default();
+        let (mut actor, _probe) = relay(&testkit).await;
+
+        actor.send(Message::Ping(4)).unwrap();
+
+        assert_eq!(4, actor.ask::<u32>(Message::Total).await.unwrap());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn advance_the_clock_to_fire_timers() {
+        let testkit = TestKit::default();
+        let (mut actor, mut probe) = relay(&testkit).await;
+
+        actor
+            .send(Message::Delayed(5, Duration::from_secs(10)))
+            .unwrap();
+        actor.run_until_idle().await.unwrap();
+
+        testkit.advance(Duration::from_secs(9)).await;
+        assert_eq!(0, actor.run_until_idle().await.unwrap());
+
+        testkit.advance(Duration::from_secs(1)).await;
+        assert_eq!(1, actor.run_until_idle().await.unwrap());
+        assert_eq!(Message::Echo(5), probe.expect());
+    }
+
+    #[tokio::test]
+    async fn record_messages_sent_to_a_dropped_probe() {
+        let testkit = TestKit::default();
+        let (mut actor, probe) = relay(&testkit).await;
+        drop(probe);
+
+        actor.send(Message::Ping(1)).unwrap();
+
+        assert!(actor.step().await.unwrap().is_err());
+        assert_eq!("peer", testkit.dead_letters()[0].target);
+    }
+}
@@ -0,0 +1,211 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::{
+    task::AbortHandle,
+    time::{Instant, MissedTickBehavior},
+};
+
+use super::{
+    mailbox::{propagate, WeakMailbox},
+    Actor,
+};
+
+/// Handle on a scheduled message, the timer keeps running when the handle is
+/// dropped.
+#[derive(Debug, Clone)]
+pub struct TimerHandle {
+    abort: AbortHandle,
+}
+
+impl TimerHandle {
+    pub fn cancel(&self) {
+        self.abort.abort();
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.abort.is_finished()
+    }
+}
+
+/// Timers started by an actor instance, cancelled when the instance stops.
+#[derive(Debug, Clone, Default)]
+pub(super) struct Timers {
+    running: Arc<Mutex<Vec<AbortHandle>>>,
+}
+
+impl Timers {
+    pub fn send_after<A: Actor + 'static>(
+        &self,
+        mailbox: WeakMailbox<A>,
+        delay: Duration,
+        message: A::Message,
+    ) -> TimerHandle {
+        self.track(tokio::spawn(propagate(async move {
+            tokio::time::sleep(delay).await;
+
+            if let Some(mailbox) = mailbox.upgrade() {
+                let _ = mailbox.send(message).await;
+            }
+        })))
+    }
+
+    pub fn send_interval<A>(
+        &self,
+        mailbox: WeakMailbox<A>,
+        period: Duration,
+        message: A::Message,
+    ) -> TimerHandle
+    where
+        A: Actor + 'static,
+        A::Message: Clone,
+    {
+        self.track(tokio::spawn(propagate(async move {
+            let mut interval = tokio::time::interval_at(Instant::now() + period, period);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            loop {
+                interval.tick().await;
+
+                let Some(mailbox) = mailbox.upgrade() else {
+                    return;
+                };
+
+                if mailbox.send(message.clone()).await.is_err() {
+                    return;
+                }
+            }
+        })))
+    }
+
+    pub fn cancel_all(&self) {
+        for timer in self.running.lock().unwrap().drain(..) {
+            timer.abort();
+        }
+    }
+
+    fn track(&self, handle: tokio::task::JoinHandle<()>) -> TimerHandle {
+        let abort = handle.abort_handle();
+        let mut running = self.running.lock().unwrap();
+
+        running.retain(|timer| !timer.is_finished());
+        running.push(abort.clone());
+
+        TimerHandle { abort }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use async_trait::async_trait;
+
+    use crate::actor::{ActorError, Context, Workspace};
+
+    use super::*;
+
+    #[derive(Debug, Clone)]
+    enum Message {
+        Tick,
+        After(Duration),
+        Every(Duration),
+        Cancel,
+        Count,
+        Crash,
+    }
+
+    #[derive(Debug, Default)]
+    struct TimerActor {
+        ticks: usize,
+        timer: Option<TimerHandle>,
+    }
+
+    #[async_trait]
+    impl Actor for TimerActor {
+        type Message = Message;
+
+        async fn handle(
+            &mut self,
+            ctx: &Context<Self>,
+            message: Self::Message,
+        ) -> Result<(), ActorError> {
+            match message {
+                Message::Tick => self.ticks += 1,
+                Message::After(delay) => {
+                    self.timer = Some(ctx.send_after(delay, Message::Tick));
+                }
+                Message::Every(period) => {
+                    self.timer = Some(ctx.send_interval(period, Message::Tick));
+                }
+                Message::Cancel => {
+                    if let Some(timer) = self.timer.take() {
+                        timer.cancel();
+                    }
+                }
+                Message::Count => ctx.reply(self.ticks),
+                Message::Crash => return Err(ActorError::Handler("crash".to_string())),
+            }
+            Ok(())
+        }
+    }
+
+    async fn ticks(mailbox: &crate::actor::mailbox::Mailbox<TimerActor>) -> usize {
+        mailbox
+            .ask(Message::Count, Duration::from_secs(1))
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn deliver_message_after_delay() {
+        let workspace = Workspace::default();
+        let (mailbox, _) = workspace.spawn("timer", TimerActor::default()).unwrap();
+
+        mailbox
+            .send(Message::After(Duration::from_secs(10)))
+            .await
+            .unwrap();
+
+        tokio::time::sleep(Duration::from_secs(9)).await;
+        assert_eq!(0, ticks(&mailbox).await);
+
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        assert_eq!(1, ticks(&mailbox).await);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn deliver_message_every_period_until_canceled() {
+        let workspace = Workspace::default();
+        let (mailbox, _) = workspace.spawn("timer", TimerActor::default()).unwrap();
+
+        mailbox
+            .send(Message::Every(Duration::from_secs(1)))
+            .await
+            .unwrap();
+
+        tokio::time::sleep(Duration::from_millis(3500)).await;
+        assert_eq!(3, ticks(&mailbox).await);
+
+        mailbox.send(Message::Cancel).await.unwrap();
+        tokio::time::sleep(Duration::from_secs(5)).await;
+        assert_eq!(3, ticks(&mailbox).await);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn cancel_timers_when_actor_restarts() {
+        let workspace = Workspace::default();
+        let (mailbox, _) = workspace
+            .spawn_supervised("timer", TimerActor::default)
+            .unwrap();
+
+        mailbox
+            .send(Message::Every(Duration::from_secs(1)))
+            .await
+            .unwrap();
+        mailbox.send(Message::Crash).await.unwrap();
+
+        tokio::time::sleep(Duration::from_secs(5)).await;
+        assert_eq!(0, ticks(&mailbox).await);
+    }
+}
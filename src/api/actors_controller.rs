@@ -0,0 +1,25 @@
+use axum::{extract::State, routing::get, Json, Router};
+use utoipa::OpenApi;
+
+use crate::{actor::metrics::ActorMetricsSnapshot, services::ServiceRegistry};
+
+#[derive(OpenApi)]
+#[openapi(paths(metrics))]
+pub struct ApiDoc;
+
+pub fn router() -> Router<ServiceRegistry> {
+    Router::new().route("/metrics", get(metrics))
+}
+
+#[utoipa::path(
+    get,
+    path = "/actors/metrics",
+    responses(
+	(status = OK, description = "Runtime metrics of every live actor", body = Vec<ActorMetricsSnapshot>)
+    )
+)]
+async fn metrics(
+    State(service_registry): State<ServiceRegistry>,
+) -> Json<Vec<ActorMetricsSnapshot>> {
+    Json(service_registry.actor_service.metrics())
+}
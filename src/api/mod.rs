@@ -1,3 +1,4 @@
+pub mod actors_controller;
 pub mod health_controller;
 pub mod operations_controller;
 pub mod root_controller;
@@ -15,6 +16,7 @@ fn swagger_ui() -> SwaggerUi {
 	nest(
 	    (path = "/api", api = root_controller::ApiDoc),
 	    (path = "/api/operations/", api = operations_controller::ApiDoc),
+	    (path = "/api/actors/", api = actors_controller::ApiDoc),
 	)
     )]
     struct ApiDoc;
@@ -29,6 +31,7 @@ pub fn router() -> Router<ServiceRegistry> {
         "/api/",
         root_controller::router()
             .nest("/operations/", operations_controller::router())
+            .nest("/actors/", actors_controller::router())
             .nest("/health", health_controller::router()),
     )
 }
@@ -36,6 +39,8 @@ pub fn router() -> Router<ServiceRegistry> {
 #[derive(Debug, Clone)]
 enum ApiError {
     NotFound,
+    Conflict,
+    Internal,
 }
 
 impl std::error::Error for ApiError {}
@@ -44,6 +49,8 @@ impl std::fmt::Display for ApiError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ApiError::NotFound => write!(f, "resource not found"),
+            ApiError::Conflict => write!(f, "conflict with the resource state"),
+            ApiError::Internal => write!(f, "internal error"),
         }
     }
 }
@@ -58,6 +65,20 @@ impl IntoResponse for ApiError {
                 },
             )
                 .into_response(),
+            ApiError::Conflict => (
+                StatusCode::CONFLICT,
+                ErrorView {
+                    error_message: "conflict with the resource state",
+                },
+            )
+                .into_response(),
+            ApiError::Internal => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ErrorView {
+                    error_message: "internal error",
+                },
+            )
+                .into_response(),
         }
     }
 }
@@ -2,42 +2,163 @@ use axum::{
     extract::{Path, State},
     http::StatusCode,
     response::IntoResponse,
-    routing::get,
+    routing::{get, post},
     Json, Router,
 };
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::error;
 use utoipa::{OpenApi, ToSchema};
+use uuid::Uuid;
 
-use crate::services::ServiceRegistry;
+use crate::{
+    operation::{self, Operation, OperationError, OperationFailure, Progress, TransitionAudit},
+    services::ServiceRegistry,
+};
 
 use super::ApiError;
 
 #[derive(OpenApi)]
-#[openapi(paths(show))]
+#[openapi(paths(show, cancel))]
 pub struct ApiDoc;
 
 pub fn router() -> Router<ServiceRegistry> {
-    Router::new().route("/{id}", get(show))
+    Router::new()
+        .route("/{id}", get(show))
+        .route("/{id}/cancel", post(cancel))
 }
 
 #[derive(Debug, Deserialize)]
 struct ShowPath {
-    id: String,
+    id: Uuid,
 }
 
 #[derive(Debug, Serialize, ToSchema)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 enum Status {
-    // Completed,
-    // Error,
-    // Queued,
-    InProgress,
+    Queued,
+    Working,
+    Canceling,
+    Failed,
+    Canceled,
+    Completed,
+}
+
+impl From<operation::State> for Status {
+    fn from(value: operation::State) -> Self {
+        match value {
+            operation::State::Queued => Status::Queued,
+            operation::State::Working => Status::Working,
+            operation::State::Canceling => Status::Canceling,
+            operation::State::Failed => Status::Failed,
+            operation::State::Canceled => Status::Canceled,
+            operation::State::Completed => Status::Completed,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct TransitionView {
+    from: Status,
+    to: Status,
+    created_at: DateTime<Utc>,
+    reason: Option<String>,
+}
+
+impl From<&TransitionAudit> for TransitionView {
+    fn from(value: &TransitionAudit) -> Self {
+        TransitionView {
+            from: value.from().into(),
+            to: value.to().into(),
+            created_at: value.created_at(),
+            reason: value.reason().map(str::to_string),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct FailureView {
+    code: String,
+    message: String,
+    details: Option<Value>,
+}
+
+impl From<&OperationFailure> for FailureView {
+    fn from(value: &OperationFailure) -> Self {
+        FailureView {
+            code: value.code().to_string(),
+            message: value.message().to_string(),
+            details: value.details().cloned(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct StepsView {
+    done: u32,
+    total: u32,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct ProgressView {
+    percent: Option<u8>,
+    steps: Option<StepsView>,
+    /// Name of the step being run.
+    step: Option<String>,
+    message: Option<String>,
+    updated_at: DateTime<Utc>,
+}
+
+impl From<&Progress> for ProgressView {
+    fn from(value: &Progress) -> Self {
+        ProgressView {
+            percent: value.percent(),
+            steps: value.steps().map(|steps| StepsView {
+                done: steps.done,
+                total: steps.total,
+            }),
+            step: value.step().map(str::to_string),
+            message: value.message().map(str::to_string),
+            updated_at: value.updated_at(),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, ToSchema)]
 struct ShowView {
     operation_id: String,
     status: Status,
+    created_at: DateTime<Utc>,
+    /// Set when the operation is failed once it passed.
+    deadline: Option<DateTime<Utc>>,
+    /// Oldest first.
+    transitions: Vec<TransitionView>,
+    /// Latest progress reported by the worker.
+    progress: Option<ProgressView>,
+    /// Set when the operation failed.
+    failure: Option<FailureView>,
+    /// Set when the operation completed with a result.
+    result: Option<Value>,
+}
+
+impl From<Operation> for ShowView {
+    fn from(value: Operation) -> Self {
+        ShowView {
+            operation_id: value.id().to_string(),
+            status: value.state().into(),
+            created_at: value.created_at(),
+            deadline: value.deadline(),
+            transitions: value
+                .transitions_audits()
+                .iter()
+                .map(TransitionView::from)
+                .collect(),
+            progress: value.progress().map(ProgressView::from),
+            failure: value.failure().map(FailureView::from),
+            result: value.result().cloned(),
+        }
+    }
 }
 
 impl IntoResponse for ShowView {
@@ -50,18 +171,44 @@ impl IntoResponse for ShowView {
     get,
     path = "/operations/:id",
     responses(
-	(status = OK, description = "Successfully retrieve the specified operation", body = ShowView)
+	(status = OK, description = "Successfully retrieve the specified operation", body = ShowView),
+	(status = NOT_FOUND, description = "No operation has this id")
     )
 )]
 async fn show(
     State(service_registry): State<ServiceRegistry>,
     Path(ShowPath { id }): Path<ShowPath>,
 ) -> Result<ShowView, ApiError> {
-    match service_registry.operation_service.find(&id) {
-        Some(operation_id) => Ok(ShowView {
-            operation_id,
-            status: Status::InProgress,
-        }),
-        None => Err(ApiError::NotFound),
+    match service_registry.operation_service.find(id.into()).await {
+        Ok(Some(operation)) => Ok(operation.into()),
+        Ok(None) => Err(ApiError::NotFound),
+        Err(e) => {
+            error!("can't lookup operation {}: {}", id, e);
+            Err(ApiError::Internal)
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/operations/:id/cancel",
+    responses(
+	(status = ACCEPTED, description = "Cancellation requested, the operation is canceling until its worker stops, or canceled when no worker took it yet", body = ShowView),
+	(status = NOT_FOUND, description = "No operation has this id"),
+	(status = CONFLICT, description = "The operation already ended")
+    )
+)]
+async fn cancel(
+    State(service_registry): State<ServiceRegistry>,
+    Path(ShowPath { id }): Path<ShowPath>,
+) -> Result<(StatusCode, Json<ShowView>), ApiError> {
+    match service_registry.operation_service.cancel(id.into()).await {
+        Ok(operation) => Ok((StatusCode::ACCEPTED, Json(operation.into()))),
+        Err(OperationError::NotFound(_)) => Err(ApiError::NotFound),
+        Err(OperationError::InvalidTransition { .. }) => Err(ApiError::Conflict),
+        Err(e) => {
+            error!("can't cancel operation {}: {}", id, e);
+            Err(ApiError::Internal)
+        }
     }
 }
@@ -1,16 +1,38 @@
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
 use tokio::sync::broadcast::{self, Receiver, Sender};
-use tracing::info;
+use tracing::{info, warn};
 
 use crate::{
+    actor::{journal::FileJournal, Workspace},
     api::router,
     error::NetherilErr,
     logging::{Logging, LoggingOptions},
-    services::{OperationService, ServiceRegistry},
+    services::{ActorService, OperationService, ServiceRegistry},
 };
 
+const WORKSPACE_NAME: &str = "netheril";
+const SHUTDOWN_DEADLINE: Duration = Duration::from_secs(10);
+const OPERATIONS_JOURNAL: &str = "operations";
+
+#[derive(Debug, Clone)]
+pub struct AppOptions {
+    /// Root of the journals kept across restarts.
+    pub data_dir: PathBuf,
+}
+
+impl Default for AppOptions {
+    fn default() -> Self {
+        AppOptions {
+            data_dir: PathBuf::from("data"),
+        }
+    }
+}
+
 pub struct App {
     #[allow(dead_code)]
     logging: Logging,
+    options: AppOptions,
 }
 
 #[derive(Debug, Clone)]
@@ -30,16 +52,28 @@ impl std::fmt::Display for Broadcast {
 
 impl App {
     pub fn new() -> Self {
+        Self::with_options(AppOptions::default())
+    }
+
+    pub fn with_options(options: AppOptions) -> Self {
         info!("configuring");
         let logging = Logging::new(LoggingOptions::default());
-        App { logging }
+        App { logging, options }
     }
 
     pub async fn run(&self) -> Result<(), Box<NetherilErr>> {
         info!("starting");
 
+        let workspace = Workspace::new(WORKSPACE_NAME);
+
+        let journal = FileJournal::open(self.options.data_dir.join(OPERATIONS_JOURNAL))
+            .await
+            .map_err(|e| NetherilErr::Service(e.to_string()))?;
+
         let services = ServiceRegistry {
-            operation_service: OperationService::new(),
+            operation_service: OperationService::with_journal(&workspace, Arc::new(journal))
+                .map_err(|e| NetherilErr::Service(e.to_string()))?,
+            actor_service: ActorService::new(&workspace),
         };
 
         let router = router().with_state(services);
@@ -72,6 +106,11 @@ impl App {
             handle.await.unwrap();
         }
 
+        let report = workspace.shutdown(SHUTDOWN_DEADLINE).await;
+        for actor in report.unfinished {
+            warn!("actor `{}` did not stop in time", actor.name);
+        }
+
         Ok(())
     }
 }
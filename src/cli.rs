@@ -1,7 +1,9 @@
-use clap::Command;
+use std::path::PathBuf;
+
+use clap::{value_parser, Arg, ArgMatches, Command};
 use tracing::trace;
 
-use crate::app::App;
+use crate::app::{App, AppOptions};
 
 const COMMAND_ROOT: &str = "netheril";
 
@@ -16,16 +18,38 @@ pub fn cmd() -> Command {
 }
 
 fn server_cmd() -> Command {
-    Command::new("server").about("run the server")
+    Command::new("server").about("run the server").arg(
+        Arg::new("data-dir")
+            .long("data-dir")
+            .value_name("DIR")
+            .value_parser(value_parser!(PathBuf))
+            .default_value("data")
+            .help("directory keeping the state that survives restarts"),
+    )
 }
 
 #[derive(Debug, Clone)]
-struct ServerCmdArgs {}
+struct ServerCmdArgs {
+    data_dir: PathBuf,
+}
+
+impl From<&ArgMatches> for ServerCmdArgs {
+    fn from(matches: &ArgMatches) -> Self {
+        ServerCmdArgs {
+            data_dir: matches
+                .get_one::<PathBuf>("data-dir")
+                .cloned()
+                .unwrap_or_default(),
+        }
+    }
+}
 
 async fn execute_server(args: ServerCmdArgs) -> Result<(), Box<dyn std::error::Error>> {
     trace!("execute_server: {:?}", args);
 
-    let app = App::new();
+    let app = App::with_options(AppOptions {
+        data_dir: args.data_dir,
+    });
     app.run().await?;
     Ok(())
 }
@@ -46,7 +70,7 @@ async fn execute_watch(args: WatchCmdArgs) -> Result<(), Box<dyn std::error::Err
 pub async fn handle_cli() -> Result<(), Box<dyn std::error::Error>> {
     let matches = cmd().get_matches();
     match matches.subcommand() {
-        Some(("server", _)) => execute_server(ServerCmdArgs {}).await,
+        Some(("server", matches)) => execute_server(matches.into()).await,
         Some(("watch", _)) => execute_watch(WatchCmdArgs {}).await,
         _ => unreachable!(),
     }
@@ -3,6 +3,7 @@ pub enum NetherilErr {
     #[allow(dead_code)]
     Logging(String),
     Api(String),
+    Service(String),
 }
 
 impl std::error::Error for NetherilErr {}
@@ -14,6 +15,7 @@ impl std::fmt::Display for NetherilErr {
         match self {
             Logging(e) => write!(f, "logging error: {}", e),
             Api(e) => write!(f, "api error: {}", e),
+            Service(e) => write!(f, "service error: {}", e),
         }
     }
 }
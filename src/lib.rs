@@ -1,13 +1,13 @@
 use cli::handle_cli;
 
-mod actor;
+pub mod actor;
 pub mod api;
 pub mod app;
 mod cli;
 pub mod domains;
 pub mod error;
 mod logging;
-mod operation;
+pub mod operation;
 pub mod services;
 pub mod version;
 
@@ -0,0 +1,73 @@
+use tokio::sync::watch;
+
+/// Tells the worker of an operation that its cancellation was requested.
+#[derive(Debug, Clone)]
+pub struct CancellationToken(watch::Receiver<bool>);
+
+impl CancellationToken {
+    pub fn is_cancelled(&self) -> bool {
+        *self.0.borrow()
+    }
+
+    /// Resolves once cancellation is requested, never when the state manager
+    /// stopped before.
+    pub async fn cancelled(&self) {
+        let mut requested = self.0.clone();
+        if requested.wait_for(|requested| *requested).await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    }
+}
+
+/// Kept by the state manager for each operation it handed a token for.
+#[derive(Debug)]
+pub(super) struct Cancellation(watch::Sender<bool>);
+
+impl Cancellation {
+    pub fn new(requested: bool) -> Self {
+        Cancellation(watch::channel(requested).0)
+    }
+
+    pub fn token(&self) -> CancellationToken {
+        CancellationToken(self.0.subscribe())
+    }
+
+    pub fn request(&self) {
+        self.0.send_replace(true);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn wake_tokens_when_cancellation_is_requested() {
+        let cancellation = Cancellation::new(false);
+        let token = cancellation.token();
+        assert!(!token.is_cancelled());
+
+        let waiting = tokio::spawn({
+            let token = token.clone();
+            async move { token.cancelled().await }
+        });
+        cancellation.request();
+
+        waiting.await.unwrap();
+        assert!(token.is_cancelled());
+        assert!(cancellation.token().is_cancelled());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn never_cancel_once_the_state_manager_is_gone() {
+        let token = Cancellation::new(false).token();
+
+        assert!(
+            tokio::time::timeout(Duration::from_secs(60), token.cancelled())
+                .await
+                .is_err()
+        );
+    }
+}
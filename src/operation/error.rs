@@ -1,15 +1,27 @@
-#![allow(unused)]
+use chrono::{DateTime, Utc};
 use tokio::sync::{mpsc::error::SendError, oneshot};
 
+use crate::actor::ActorError;
+
 use super::{states::State, Id};
 
 #[derive(Debug)]
 pub enum OperationError {
     NotFound(Id),
-    InvalidTransition { from: State, to: State },
+    InvalidTransition {
+        from: State,
+        to: State,
+    },
     Sender,
     Receiver,
-    StateMismatch { expected: State, current: State },
+    /// Progress and heartbeats are only sent while the operation is
+    /// `Working`.
+    NotWorking(State),
+    /// The result given to `Sentinel::complete_with` can't be serialized.
+    InvalidResult(String),
+    /// The operation was still unfinished at its deadline.
+    DeadlineExceeded(DateTime<Utc>),
+    Actor(ActorError),
 }
 
 impl std::error::Error for OperationError {}
@@ -23,13 +35,14 @@ impl std::fmt::Display for OperationError {
             }
             OperationError::Sender => write!(f, "sender error on channel"),
             OperationError::Receiver => write!(f, "receiver error on channel"),
-            OperationError::StateMismatch { expected, current } => {
-                write!(
-                    f,
-                    "expected state `{}`, current state: `{}`",
-                    expected, current
-                )
+            OperationError::NotWorking(state) => {
+                write!(f, "operation is `{}`, not working", state)
+            }
+            OperationError::InvalidResult(e) => write!(f, "invalid operation result: {}", e),
+            OperationError::DeadlineExceeded(deadline) => {
+                write!(f, "operation not finished by its deadline {}", deadline)
             }
+            OperationError::Actor(e) => write!(f, "state manager error: {}", e),
         }
     }
 }
@@ -45,3 +58,9 @@ impl From<oneshot::error::RecvError> for OperationError {
         OperationError::Receiver
     }
 }
+
+impl From<ActorError> for OperationError {
+    fn from(value: ActorError) -> Self {
+        OperationError::Actor(value)
+    }
+}
@@ -1,24 +1,45 @@
 #![allow(unused)]
-use std::collections::BTreeMap;
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
 
 use async_trait::async_trait;
-use error::OperationError;
-use operation_model::Operation;
-use sentinel::Sentinel;
-use states::State;
-use tokio::sync::oneshot;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::{task::JoinHandle, time::Instant};
+use tracing::warn;
 
-use crate::actor::{Actor, ActorError, Context};
+use crate::actor::{
+    bus::Topic,
+    journal::{Journal, MemoryJournal},
+    mailbox::{Mailbox, MailboxOptions},
+    persistence::{Persisted, Persistent, PersistentActor, PersistentContext},
+    ActorError, Workspace,
+};
 
+mod cancellation;
 mod error;
 mod operation_model;
 mod sentinel;
 mod states;
 
-#[derive(Debug, Clone, Copy, PartialEq, Ord, PartialOrd, Eq)]
+use cancellation::Cancellation;
+pub use cancellation::CancellationToken;
+pub use error::OperationError;
+use operation_model::Outcome;
+pub use operation_model::{Operation, OperationFailure, Progress, Steps, TransitionAudit};
+pub use sentinel::Sentinel;
+pub use states::State;
+
+#[derive(Debug, Clone, Copy, PartialEq, Ord, PartialOrd, Eq, Serialize, Deserialize)]
 pub struct Id(uuid::Uuid);
 
+const OPERATION_STATE_MANAGER_NAME: &str = "operation-state-manager";
 const OPERATION_STATE_MANAGER_CAPACITY: usize = 100;
+const OPERATION_STATE_MANAGER_TIMEOUT: Duration = Duration::from_secs(5);
+/// Silence after which an operation whose worker sent heartbeats is
+/// abandoned.
+const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(30);
+/// Period of the checks of heartbeats and deadlines.
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
 
 impl Id {
     pub fn generate() -> Id {
@@ -26,65 +47,535 @@ impl Id {
     }
 }
 
+impl From<uuid::Uuid> for Id {
+    fn from(value: uuid::Uuid) -> Self {
+        Id(value)
+    }
+}
+
 impl std::fmt::Display for Id {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.0)
     }
 }
 
+/// Published on `OPERATION_TRANSITIONS` each time an operation changes state.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OperationTransition {
+    pub id: Id,
+    pub from: State,
+    pub to: State,
+}
+
+pub const OPERATION_TRANSITIONS: Topic<OperationTransition> = Topic::new("operation.transitions");
+
+/// Reason recorded when an operation still `Working` at startup is failed.
+pub const INTERRUPTED_REASON: &str = "interrupted by restart";
+/// Failure code of the operations interrupted by a restart.
+pub const INTERRUPTED_CODE: &str = "INTERRUPTED";
+/// Reason recorded when the worker of an operation dropped its sentinels or
+/// stopped heartbeating before the operation ended.
+pub const ABANDONED_REASON: &str = "abandoned";
+/// Failure code of the abandoned operations.
+pub const ABANDONED_CODE: &str = "ABANDONED";
+/// Reason recorded when an operation is still unfinished at its deadline.
+pub const DEADLINE_EXCEEDED_REASON: &str = "deadline exceeded";
+/// Reason recorded when a `Queued` operation no worker took is canceled.
+pub const CANCELED_WHILE_QUEUED_REASON: &str = "canceled while queued";
+
+type Operations = BTreeMap<Id, Operation>;
+
+/// The state manager as spawned, its operations are rebuilt from its journal.
+type StateManager = Persistent<OperationStateManagerActor>;
+
+/// Worker of an unfinished operation, known from the sentinels created for it.
+struct Worker {
+    cancellation: Cancellation,
+    /// Sentinels not dropped yet, clones count as one.
+    sentinels: usize,
+    heartbeat_at: Option<Instant>,
+}
+
+#[derive(Default)]
 struct OperationStateManagerActor {
-    operations: BTreeMap<Id, Operation>,
-    receiver: tokio::sync::mpsc::Receiver<Message>,
+    /// Workers of the unfinished operations a sentinel was created for.
+    workers: BTreeMap<Id, Worker>,
+    /// Deadlines of the unfinished operations, checked against the wall
+    /// clock.
+    deadlines: BTreeMap<Id, DateTime<Utc>>,
+    /// Latest progress of the operations, reported too often to be journaled
+    /// it is lost on restart.
+    progress: BTreeMap<Id, Progress>,
 }
 
-impl OperationStateManagerActor {
-    pub fn new(receiver: tokio::sync::mpsc::Receiver<Message>) -> Self {
-        OperationStateManagerActor {
-            operations: BTreeMap::new(),
-            receiver,
-        }
-    }
+#[derive(Debug, Serialize, Deserialize)]
+enum Event {
+    Created {
+        id: Id,
+        created_at: DateTime<Utc>,
+        #[serde(default)]
+        deadline: Option<DateTime<Utc>>,
+    },
+    Transitioned {
+        id: Id,
+        audit: TransitionAudit,
+        outcome: Option<Outcome>,
+    },
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 enum Message {
     Quit,
     LookupOperation {
         id: Id,
-        reply_to: oneshot::Sender<Option<Operation>>,
     },
     NewOperation {
-        reply_to: oneshot::Sender<Id>,
+        deadline: Option<DateTime<Utc>>,
     },
     UpdateOperation {
         id: Id,
         from: State,
         to: State,
+        outcome: Option<Outcome>,
+    },
+    ReportProgress {
+        id: Id,
+        progress: Progress,
+    },
+    /// Replies `NewSentinelReply`.
+    NewSentinel {
+        id: Id,
+    },
+    RequestCancel {
+        id: Id,
     },
+    /// Fails the operations left `Working` by the previous process and those
+    /// past their deadline.
+    FailInterrupted,
+    /// Sent when the last clone of a sentinel is dropped.
+    SentinelDropped {
+        id: Id,
+    },
+    Heartbeat {
+        id: Id,
+    },
+    /// Abandons the operations whose worker stopped heartbeating and fails
+    /// those past their deadline.
+    Check,
+}
+
+// replies of the messages asked by `OperationStateManagerHandle`, named by
+// both the handler and the handle so they can't disagree on the type.
+type NewOperationReply = Id;
+type LookupOperationReply = Option<Operation>;
+/// The state of the operation and its cancellation token, `None` when it is
+/// unknown.
+type NewSentinelReply = Option<(State, CancellationToken)>;
+type RequestCancelReply = Result<Operation, OperationError>;
+
+impl OperationStateManagerActor {
+    /// Records the transition when `id` is still in `from`, updates from a
+    /// stale state are ignored.
+    async fn transition(
+        ctx: &PersistentContext<Self>,
+        persisted: &mut Persisted<Self>,
+        id: Id,
+        from: State,
+        to: State,
+        reason: Option<String>,
+        outcome: Option<Outcome>,
+    ) -> Result<(), ActorError> {
+        let Some(current) = persisted.state().get(&id).map(Operation::state) else {
+            return Ok(());
+        };
+
+        // a worker learns about cancellation from its token, its sentinel
+        // still reports from the state it last knew.
+        let from = match current {
+            State::Canceling if matches!(from, State::Queued | State::Working) => {
+                if !to.is_terminal() {
+                    return Ok(());
+                }
+                State::Canceling
+            }
+            current if current == from => from,
+            _ => return Ok(()),
+        };
+
+        let audit = TransitionAudit::new(from.clone(), to.clone(), reason);
+        persisted
+            .persist(Event::Transitioned { id, audit, outcome })
+            .await?;
+
+        let transition = OperationTransition { id, from, to };
+        ctx.bus().publish(&OPERATION_TRANSITIONS, transition)?;
+        Ok(())
+    }
+
+    /// Moves a `Queued` or `Working` operation to `Canceling` and wakes the
+    /// tokens of its worker. A `Queued` operation without a worker has no one
+    /// to confirm, it is canceled right away.
+    async fn request_cancel(
+        &mut self,
+        ctx: &PersistentContext<Self>,
+        persisted: &mut Persisted<Self>,
+        id: Id,
+    ) -> Result<Result<Operation, OperationError>, ActorError> {
+        let Some(from) = persisted.state().get(&id).map(Operation::state) else {
+            return Ok(Err(OperationError::NotFound(id)));
+        };
+
+        match from {
+            State::Queued if !self.workers.contains_key(&id) => {
+                let reason = Some(CANCELED_WHILE_QUEUED_REASON.to_string());
+                Self::transition(ctx, persisted, id, from, State::Canceled, reason, None).await?;
+                self.release(persisted.state(), id);
+            }
+            State::Queued | State::Working => {
+                Self::transition(ctx, persisted, id, from, State::Canceling, None, None).await?;
+                if let Some(worker) = self.workers.get(&id) {
+                    worker.cancellation.request();
+                }
+            }
+            State::Canceling => {}
+            from => {
+                return Ok(Err(OperationError::InvalidTransition {
+                    from,
+                    to: State::Canceling,
+                }))
+            }
+        }
+
+        Ok(self
+            .operation(persisted.state(), id)
+            .ok_or(OperationError::NotFound(id)))
+    }
+
+    /// Operation `id` with the latest progress reported by its worker.
+    fn operation(&self, operations: &Operations, id: Id) -> Option<Operation> {
+        let mut operation = operations.get(&id)?.clone();
+        if let Some(progress) = self.progress.get(&id) {
+            operation.report(progress.clone());
+        }
+        Some(operation)
+    }
+
+    /// Fails a `Working` operation, or cancels a `Canceling` one, left
+    /// without a worker.
+    async fn abandon(
+        &mut self,
+        ctx: &PersistentContext<Self>,
+        persisted: &mut Persisted<Self>,
+        id: Id,
+        message: &str,
+    ) -> Result<(), ActorError> {
+        let reason = Some(ABANDONED_REASON.to_string());
+
+        match persisted.state().get(&id).map(Operation::state) {
+            Some(State::Working) => {
+                warn!("operation {} was abandoned: {}", id, message);
+                let failure = OperationFailure::new(ABANDONED_CODE, message);
+                let outcome = Some(Outcome::Failed(failure));
+                Self::transition(
+                    ctx,
+                    persisted,
+                    id,
+                    State::Working,
+                    State::Failed,
+                    reason,
+                    outcome,
+                )
+                .await?;
+            }
+            Some(State::Canceling) => {
+                Self::transition(
+                    ctx,
+                    persisted,
+                    id,
+                    State::Canceling,
+                    State::Canceled,
+                    reason,
+                    None,
+                )
+                .await?;
+            }
+            _ => {}
+        }
+
+        self.release(persisted.state(), id);
+        Ok(())
+    }
+
+    /// Fails the operations still unfinished past their deadline.
+    async fn expire_overdue(
+        &mut self,
+        ctx: &PersistentContext<Self>,
+        persisted: &mut Persisted<Self>,
+    ) -> Result<(), ActorError> {
+        let now = Utc::now();
+        let overdue: Vec<Id> = self
+            .deadlines
+            .iter()
+            .filter(|(_, deadline)| **deadline <= now)
+            .map(|(id, _)| *id)
+            .collect();
+
+        for id in overdue {
+            self.expire(ctx, persisted, id).await?;
+            self.deadlines.remove(&id);
+        }
+        Ok(())
+    }
+
+    /// Fails the operation `id` when it is still unfinished and tells its
+    /// worker to stop.
+    async fn expire(
+        &mut self,
+        ctx: &PersistentContext<Self>,
+        persisted: &mut Persisted<Self>,
+        id: Id,
+    ) -> Result<(), ActorError> {
+        let Some(operation) = persisted.state().get(&id) else {
+            return Ok(());
+        };
+        let (from, deadline) = (operation.state(), operation.deadline());
+        let Some(deadline) = deadline else {
+            return Ok(());
+        };
+
+        let reason = Some(DEADLINE_EXCEEDED_REASON.to_string());
+        match from {
+            State::Queued | State::Working => {
+                warn!("operation {} exceeded its deadline {}", id, deadline);
+                let failure = OperationError::DeadlineExceeded(deadline).into();
+                let outcome = Some(Outcome::Failed(failure));
+                Self::transition(ctx, persisted, id, from, State::Failed, reason, outcome).await?;
+            }
+            State::Canceling => {
+                Self::transition(ctx, persisted, id, from, State::Canceled, reason, None).await?;
+            }
+            _ => return Ok(()),
+        }
+
+        if let Some(worker) = self.workers.get(&id) {
+            worker.cancellation.request();
+        }
+        self.release(persisted.state(), id);
+        Ok(())
+    }
+
+    /// Forgets the worker, the deadline and the progress of `id` once the
+    /// operation ended.
+    fn release(&mut self, operations: &Operations, id: Id) {
+        if operations
+            .get(&id)
+            .is_none_or(|operation| operation.state().is_terminal())
+        {
+            self.workers.remove(&id);
+            self.deadlines.remove(&id);
+            self.progress.remove(&id);
+        }
+    }
 }
 
 #[async_trait]
-impl Actor for OperationStateManagerActor {
+impl PersistentActor for OperationStateManagerActor {
     type Message = Message;
+    type Event = Event;
+    type State = Operations;
+
+    fn mailbox_options() -> MailboxOptions {
+        MailboxOptions {
+            capacity: OPERATION_STATE_MANAGER_CAPACITY,
+            ..Default::default()
+        }
+    }
+
+    async fn recovered(
+        &mut self,
+        ctx: &PersistentContext<Self>,
+        operations: &Operations,
+    ) -> Result<(), ActorError> {
+        self.deadlines = operations
+            .values()
+            .filter(|operation| !operation.state().is_terminal())
+            .filter_map(|operation| Some((operation.id(), operation.deadline()?)))
+            .collect();
+
+        ctx.send_interval(CHECK_INTERVAL, Message::Check);
+        Ok(())
+    }
+
+    fn apply(operations: &mut Operations, event: &Event) {
+        match event {
+            Event::Created {
+                id,
+                created_at,
+                deadline,
+            } => {
+                operations.insert(*id, Operation::new(*id, *created_at, *deadline));
+            }
+            Event::Transitioned { id, audit, outcome } => {
+                if let Some(operation) = operations.get_mut(id) {
+                    operation.record(audit.clone(), outcome.clone());
+                }
+            }
+        }
+    }
 
-    async fn handle(&mut self, _ctx: &Context, message: Self::Message) -> Result<(), ActorError> {
+    async fn handle(
+        &mut self,
+        ctx: &PersistentContext<Self>,
+        persisted: &mut Persisted<Self>,
+        message: Self::Message,
+    ) -> Result<(), ActorError> {
         use Message::*;
 
         match message {
-            NewOperation { reply_to } => {
-                let operation = Operation::new();
-                let id = operation.id();
-                self.operations.insert(id, operation);
-                reply_to.send(id);
-            }
-            LookupOperation { id, reply_to } => {
-                let operation = self.operations.get(&id).cloned();
-                reply_to.send(operation);
-            }
-            UpdateOperation { id, from, to } => {
-                if let Some(operation) = self.operations.get_mut(&id) {
-                    operation.apply(from, to);
+            NewOperation { deadline } => {
+                let id = Id::generate();
+                let created_at = Utc::now();
+                persisted
+                    .persist(Event::Created {
+                        id,
+                        created_at,
+                        deadline,
+                    })
+                    .await?;
+                if let Some(deadline) = deadline {
+                    self.deadlines.insert(id, deadline);
                 }
+                ctx.reply::<NewOperationReply>(id);
+            }
+            LookupOperation { id } => {
+                let operation = self.operation(persisted.state(), id);
+                ctx.reply::<LookupOperationReply>(operation);
+            }
+            UpdateOperation {
+                id,
+                from,
+                to,
+                outcome,
+            } => {
+                Self::transition(ctx, persisted, id, from, to, None, outcome).await?;
+                self.release(persisted.state(), id);
+            }
+            NewSentinel { id } => {
+                let sentinel = persisted.state().get(&id).map(|operation| {
+                    let state = operation.state();
+                    let worker = self.workers.entry(id).or_insert_with(|| Worker {
+                        cancellation: Cancellation::new(state == State::Canceling),
+                        sentinels: 0,
+                        heartbeat_at: None,
+                    });
+                    worker.sentinels += 1;
+                    (state, worker.cancellation.token())
+                });
+                self.release(persisted.state(), id);
+                ctx.reply::<NewSentinelReply>(sentinel);
+            }
+            RequestCancel { id } => {
+                let canceled = self.request_cancel(ctx, persisted, id).await?;
+                ctx.reply::<RequestCancelReply>(canceled);
+            }
+            ReportProgress { id, progress } => {
+                // progress sent before the operation ended is handled late,
+                // its priority is low.
+                let working = persisted
+                    .state()
+                    .get(&id)
+                    .is_some_and(|operation| operation.state() == State::Working);
+                if working {
+                    self.progress.insert(id, progress);
+                }
+            }
+            FailInterrupted => {
+                let canceling: Vec<Id> = persisted
+                    .state()
+                    .values()
+                    .filter(|operation| operation.state() == State::Canceling)
+                    .map(Operation::id)
+                    .collect();
+
+                // the work stopped with the previous process.
+                for id in canceling {
+                    let reason = Some(INTERRUPTED_REASON.to_string());
+                    Self::transition(
+                        ctx,
+                        persisted,
+                        id,
+                        State::Canceling,
+                        State::Canceled,
+                        reason,
+                        None,
+                    )
+                    .await?;
+                }
+
+                let interrupted: Vec<Id> = persisted
+                    .state()
+                    .values()
+                    .filter(|operation| operation.state() == State::Working)
+                    .map(Operation::id)
+                    .collect();
+
+                for id in interrupted {
+                    warn!("operation {} was interrupted by a restart", id);
+                    let reason = Some(INTERRUPTED_REASON.to_string());
+                    let failure = OperationFailure::new(INTERRUPTED_CODE, INTERRUPTED_REASON);
+                    let outcome = Some(Outcome::Failed(failure));
+                    Self::transition(
+                        ctx,
+                        persisted,
+                        id,
+                        State::Working,
+                        State::Failed,
+                        reason,
+                        outcome,
+                    )
+                    .await?;
+                }
+
+                // deadlines passed while stopped expire before the first
+                // lookup.
+                self.expire_overdue(ctx, persisted).await?;
+            }
+            SentinelDropped { id } => {
+                let Some(worker) = self.workers.get_mut(&id) else {
+                    return Ok(());
+                };
+                worker.sentinels = worker.sentinels.saturating_sub(1);
+                if worker.sentinels == 0 {
+                    self.abandon(ctx, persisted, id, "every sentinel was dropped")
+                        .await?;
+                    // a `Queued` operation is left for a new worker.
+                    self.workers.remove(&id);
+                }
+            }
+            Heartbeat { id } => {
+                if let Some(worker) = self.workers.get_mut(&id) {
+                    worker.heartbeat_at = Some(Instant::now());
+                }
+            }
+            Check => {
+                let now = Instant::now();
+                let silent: Vec<Id> = self
+                    .workers
+                    .iter()
+                    .filter(|(_, worker)| {
+                        worker.heartbeat_at.is_some_and(|heartbeat_at| {
+                            now.duration_since(heartbeat_at) >= HEARTBEAT_TIMEOUT
+                        })
+                    })
+                    .map(|(id, _)| *id)
+                    .collect();
+
+                for id in silent {
+                    let message = format!("no heartbeat for {}s", HEARTBEAT_TIMEOUT.as_secs());
+                    self.abandon(ctx, persisted, id, &message).await?;
+                }
+
+                self.expire_overdue(ctx, persisted).await?;
             }
             Quit => {}
         }
@@ -92,68 +583,113 @@ impl Actor for OperationStateManagerActor {
     }
 }
 
-struct OperationStateManagerHandle {
-    sender: tokio::sync::mpsc::Sender<Message>,
+#[derive(Debug, Clone)]
+pub struct OperationStateManagerHandle {
+    mailbox: Mailbox<StateManager>,
 }
 
 impl OperationStateManagerHandle {
-    pub fn new() -> Self {
-        let (sender, receiver) = tokio::sync::mpsc::channel(OPERATION_STATE_MANAGER_CAPACITY);
-        let manager = OperationStateManagerActor::new(receiver);
-        let handle = OperationStateManagerHandle { sender };
+    /// Spawns a state manager keeping its operations in memory.
+    pub fn new(workspace: &Workspace) -> Result<Self, OperationError> {
+        Self::with_journal(workspace, Arc::new(MemoryJournal::default()))
+    }
+
+    /// Spawns a state manager recording its operations in `journal`, the
+    /// operations left `Working` by a previous process, or past their
+    /// deadline, are failed first. The state manager is supervised, a failure
+    /// restarts it from `journal`.
+    pub fn with_journal(
+        workspace: &Workspace,
+        journal: Arc<dyn Journal>,
+    ) -> Result<Self, OperationError> {
+        Self::spawn(workspace, journal).map(|(state_manager, _)| state_manager)
+    }
 
-        tokio::spawn(execute_operation_state_manager(manager));
+    /// Same as `with_journal`, the returned handle resolves once the state
+    /// manager stopped.
+    fn spawn(
+        workspace: &Workspace,
+        journal: Arc<dyn Journal>,
+    ) -> Result<(Self, JoinHandle<()>), OperationError> {
+        let (mailbox, stopped) = workspace
+            .spawn_supervised(OPERATION_STATE_MANAGER_NAME, move || {
+                Persistent::new(OperationStateManagerActor::default(), journal.clone())
+            })?;
+        mailbox.try_send(Message::FailInterrupted)?;
 
-        handle
+        Ok((OperationStateManagerHandle { mailbox }, stopped))
     }
 
     pub async fn new_operation(&self) -> Result<Id, OperationError> {
-        let (tx, rx) = oneshot::channel();
-        self.sender
-            .send(Message::NewOperation { reply_to: tx })
-            .await?;
-        Ok(rx.await?)
+        self.ask::<NewOperationReply>(Message::NewOperation { deadline: None })
+            .await
+    }
+
+    /// Creates an operation failed when still unfinished at `deadline`, the
+    /// wall clock is checked against it every `CHECK_INTERVAL`.
+    pub async fn new_operation_with_deadline(
+        &self,
+        deadline: DateTime<Utc>,
+    ) -> Result<Id, OperationError> {
+        self.ask::<NewOperationReply>(Message::NewOperation {
+            deadline: Some(deadline),
+        })
+        .await
     }
 
     pub async fn lookup_operation(&self, id: &Id) -> Result<Option<Operation>, OperationError> {
-        let (tx, rx) = oneshot::channel();
-        self.sender
-            .send(Message::LookupOperation {
-                id: *id,
-                reply_to: tx,
-            })
-            .await?;
-        Ok(rx.await?)
+        self.ask::<LookupOperationReply>(Message::LookupOperation { id: *id })
+            .await
     }
 
     pub async fn new_sentinel(&self, id: Id) -> Result<Sentinel, OperationError> {
-        match self.lookup_operation(&id).await? {
-            Some(operation) => Ok(Sentinel::reify(id, operation.state(), self.sender.clone())),
+        match self
+            .ask::<NewSentinelReply>(Message::NewSentinel { id })
+            .await?
+        {
+            Some((state, token)) => Ok(Sentinel::reify(id, state, self.mailbox.clone(), token)),
             None => Err(OperationError::NotFound(id)),
         }
     }
-}
 
-async fn execute_operation_state_manager(mut manager: OperationStateManagerActor) {
-    let ctx = Context::new();
-    while let Some(message) = manager.receiver.recv().await {
-        manager.handle(&ctx, message).await.unwrap();
+    /// Moves the operation to `Canceling` until its worker confirms it
+    /// stopped, or straight to `Canceled` when it is `Queued` without a
+    /// sentinel. Fails when the operation already ended.
+    pub async fn request_cancel(&self, id: Id) -> Result<Operation, OperationError> {
+        self.ask::<RequestCancelReply>(Message::RequestCancel { id })
+            .await?
+    }
+
+    async fn ask<R: Send + 'static>(&self, message: Message) -> Result<R, OperationError> {
+        Ok(self
+            .mailbox
+            .ask(message, OPERATION_STATE_MANAGER_TIMEOUT)
+            .await?)
     }
 }
 
 #[cfg(test)]
 mod test {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use crate::actor::{
+        bus::Lag,
+        journal::{FileJournal, JournalEntry, Snapshot},
+        supervisor::SupervisorEvent,
+        testkit::{Scratch, TestKit},
+    };
+
     use super::*;
 
     #[tokio::test]
     async fn create_new_operation() {
-        let op_state = OperationStateManagerHandle::new();
+        let op_state = OperationStateManagerHandle::new(&Workspace::default()).unwrap();
         let id = op_state.new_operation().await.unwrap();
     }
 
     #[tokio::test]
     async fn return_true_when_operation_exists() {
-        let op_state = OperationStateManagerHandle::new();
+        let op_state = OperationStateManagerHandle::new(&Workspace::default()).unwrap();
         let id = op_state.new_operation().await.unwrap();
         let operation = op_state.lookup_operation(&id).await.unwrap().unwrap();
         assert_eq!(operation.id(), id);
@@ -161,7 +697,7 @@ mod test {
 
     #[tokio::test]
     async fn cant_create_sentinel_when_operation_does_not_exist() {
-        let op_state = OperationStateManagerHandle::new();
+        let op_state = OperationStateManagerHandle::new(&Workspace::default()).unwrap();
         let id = Id::generate();
         assert!(matches!(
             op_state.new_sentinel(id).await,
@@ -171,7 +707,7 @@ mod test {
 
     #[tokio::test]
     async fn create_sentinel_when_operation_exists() {
-        let op_state = OperationStateManagerHandle::new();
+        let op_state = OperationStateManagerHandle::new(&Workspace::default()).unwrap();
         let id = op_state.new_operation().await.unwrap();
         let sentinel = op_state.new_sentinel(id).await.unwrap();
         assert_eq!(sentinel.id(), id);
@@ -179,7 +715,7 @@ mod test {
 
     #[tokio::test]
     async fn update_operation_state_from_sentinel() {
-        let op_state = OperationStateManagerHandle::new();
+        let op_state = OperationStateManagerHandle::new(&Workspace::default()).unwrap();
         let id = op_state.new_operation().await.unwrap();
         let mut sentinel = op_state.new_sentinel(id).await.unwrap();
 
@@ -191,4 +727,608 @@ mod test {
         let operation = op_state.lookup_operation(&id).await.unwrap().unwrap();
         assert_eq!(operation.state(), State::Completed);
     }
+
+    #[tokio::test]
+    async fn publish_operation_transitions() {
+        let workspace = Workspace::default();
+        let mut transitions = workspace
+            .bus()
+            .subscribe(&OPERATION_TRANSITIONS, Lag::Skip)
+            .unwrap();
+        let op_state = OperationStateManagerHandle::new(&workspace).unwrap();
+        let id = op_state.new_operation().await.unwrap();
+        let mut sentinel = op_state.new_sentinel(id).await.unwrap();
+
+        sentinel.start().await.unwrap();
+
+        assert_eq!(
+            Some(OperationTransition {
+                id,
+                from: State::Queued,
+                to: State::Working,
+            }),
+            transitions.recv().await
+        );
+    }
+
+    #[tokio::test]
+    async fn ignore_updates_from_stale_state() {
+        let testkit = TestKit::default();
+        let mut transitions = testkit
+            .bus()
+            .subscribe(&OPERATION_TRANSITIONS, Lag::Skip)
+            .unwrap();
+        let mut actor = testkit
+            .spawn(
+                OPERATION_STATE_MANAGER_NAME,
+                Persistent::new(
+                    OperationStateManagerActor::default(),
+                    Arc::new(MemoryJournal::default()),
+                ),
+            )
+            .await
+            .unwrap();
+        let id: Id = actor
+            .ask(Message::NewOperation { deadline: None })
+            .await
+            .unwrap();
+
+        actor
+            .send(Message::UpdateOperation {
+                id,
+                from: State::Working,
+                to: State::Completed,
+                outcome: None,
+            })
+            .unwrap();
+        actor
+            .send(Message::UpdateOperation {
+                id,
+                from: State::Queued,
+                to: State::Working,
+                outcome: None,
+            })
+            .unwrap();
+        assert_eq!(2, actor.run_until_idle().await.unwrap());
+
+        assert_eq!(State::Working, actor.actor().state()[&id].state());
+        assert_eq!(
+            Some(OperationTransition {
+                id,
+                from: State::Queued,
+                to: State::Working,
+            }),
+            transitions.recv().await
+        );
+    }
+
+    async fn restart(journal: Arc<dyn Journal>) -> OperationStateManagerHandle {
+        OperationStateManagerHandle::with_journal(&Workspace::default(), journal).unwrap()
+    }
+
+    /// State manager in a workspace of its own, stopped with `stop`.
+    fn start(
+        journal: Arc<dyn Journal>,
+    ) -> (Workspace, OperationStateManagerHandle, JoinHandle<()>) {
+        let workspace = Workspace::default();
+        let (op_state, stopped) = OperationStateManagerHandle::spawn(&workspace, journal).unwrap();
+        (workspace, op_state, stopped)
+    }
+
+    /// Returns once the state manager stopped, before another one replays
+    /// its journal.
+    async fn stop(workspace: Workspace, stopped: JoinHandle<()>) {
+        workspace.shutdown(Duration::from_secs(1)).await;
+        stopped.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn fail_operations_interrupted_by_restart() {
+        let journal = Arc::new(MemoryJournal::default());
+        let (workspace, op_state, stopped) = start(journal.clone());
+        let queued = op_state.new_operation().await.unwrap();
+        let working = op_state.new_operation().await.unwrap();
+        let mut sentinel = op_state.new_sentinel(working).await.unwrap();
+        sentinel.start().await.unwrap();
+        stop(workspace, stopped).await;
+
+        let op_state = restart(journal).await;
+
+        let operation = op_state.lookup_operation(&working).await.unwrap().unwrap();
+        assert_eq!(State::Failed, operation.state());
+        assert_eq!(
+            Some(INTERRUPTED_REASON),
+            operation.transitions_audits().last().unwrap().reason()
+        );
+        assert_eq!(INTERRUPTED_CODE, operation.failure().unwrap().code());
+        let operation = op_state.lookup_operation(&queued).await.unwrap().unwrap();
+        assert_eq!(State::Queued, operation.state());
+    }
+
+    /// Memory journal refusing appends while `failing` is set.
+    #[derive(Debug, Default)]
+    struct FailingJournal {
+        inner: MemoryJournal,
+        failing: AtomicBool,
+    }
+
+    #[async_trait]
+    impl Journal for FailingJournal {
+        async fn append(&self, id: &str, entry: JournalEntry) -> Result<(), ActorError> {
+            if self.failing.load(Ordering::SeqCst) {
+                return Err(ActorError::Journal("disk full".to_string()));
+            }
+            self.inner.append(id, entry).await
+        }
+
+        async fn replay(&self, id: &str, after: u64) -> Result<Vec<JournalEntry>, ActorError> {
+            self.inner.replay(id, after).await
+        }
+
+        async fn load_snapshot(&self, id: &str) -> Result<Option<Snapshot>, ActorError> {
+            self.inner.load_snapshot(id).await
+        }
+
+        async fn save_snapshot(&self, id: &str, snapshot: Snapshot) -> Result<(), ActorError> {
+            self.inner.save_snapshot(id, snapshot).await
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn recover_from_journal_failure() {
+        let journal = Arc::new(FailingJournal::default());
+        let (workspace, op_state, _) = start(journal.clone());
+        let mut events = workspace.events();
+        let id = op_state.new_operation().await.unwrap();
+
+        journal.failing.store(true, Ordering::SeqCst);
+        assert!(op_state.new_operation().await.is_err());
+        journal.failing.store(false, Ordering::SeqCst);
+        while !matches!(
+            events.recv().await.unwrap(),
+            SupervisorEvent::Restarted { .. }
+        ) {}
+
+        let operation = op_state.lookup_operation(&id).await.unwrap().unwrap();
+        assert_eq!(State::Queued, operation.state());
+        assert!(op_state.new_operation().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn keep_operations_in_file_journal() {
+        let scratch = Scratch::new("operations");
+        let journal = FileJournal::open(scratch.path()).await.unwrap();
+        let (workspace, op_state, stopped) = start(Arc::new(journal));
+        let id = op_state.new_operation().await.unwrap();
+        let created = op_state.lookup_operation(&id).await.unwrap().unwrap();
+        stop(workspace, stopped).await;
+
+        let journal = FileJournal::open(scratch.path()).await.unwrap();
+        let op_state = restart(Arc::new(journal)).await;
+
+        let operation = op_state.lookup_operation(&id).await.unwrap().unwrap();
+        assert_eq!(created.created_at(), operation.created_at());
+        assert_eq!(State::Queued, operation.state());
+    }
+
+    #[tokio::test]
+    async fn keep_the_outcome_of_finished_operations() {
+        let op_state = OperationStateManagerHandle::new(&Workspace::default()).unwrap();
+        let failed = op_state.new_operation().await.unwrap();
+        let completed = op_state.new_operation().await.unwrap();
+
+        let mut sentinel = op_state.new_sentinel(failed).await.unwrap();
+        sentinel.start().await.unwrap();
+        sentinel
+            .fail(
+                OperationFailure::new("IMAGE_NOT_FOUND", "no image `debian`")
+                    .with_details(serde_json::json!({ "image": "debian" })),
+            )
+            .await
+            .unwrap();
+
+        let mut sentinel = op_state.new_sentinel(completed).await.unwrap();
+        sentinel.start().await.unwrap();
+        sentinel
+            .complete_with(&serde_json::json!({ "vm_id": 7 }))
+            .await
+            .unwrap();
+
+        let operation = op_state.lookup_operation(&failed).await.unwrap().unwrap();
+        let failure = operation.failure().unwrap();
+        assert_eq!("IMAGE_NOT_FOUND", failure.code());
+        assert_eq!("no image `debian`", failure.message());
+        assert_eq!(
+            Some(&serde_json::json!({ "image": "debian" })),
+            failure.details()
+        );
+        assert!(operation.result().is_none());
+
+        let operation = op_state
+            .lookup_operation(&completed)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(Some(&serde_json::json!({ "vm_id": 7 })), operation.result());
+        assert!(operation.failure().is_none());
+    }
+
+    #[tokio::test]
+    async fn keep_latest_progress_of_working_operation() {
+        let testkit = TestKit::default();
+        let mut actor = testkit
+            .spawn(
+                OPERATION_STATE_MANAGER_NAME,
+                Persistent::new(
+                    OperationStateManagerActor::default(),
+                    Arc::new(MemoryJournal::default()),
+                ),
+            )
+            .await
+            .unwrap();
+        let id: Id = actor
+            .ask(Message::NewOperation { deadline: None })
+            .await
+            .unwrap();
+        let mut sentinel = Sentinel::new(id, actor.mailbox(), Cancellation::new(false).token());
+        sentinel.start().await.unwrap();
+
+        sentinel
+            .report(
+                Progress::from_percent(40)
+                    .with_step("import image")
+                    .with_message("4 of 10 GiB"),
+            )
+            .unwrap();
+        actor.run_until_idle().await.unwrap();
+        let operation: Option<Operation> =
+            actor.ask(Message::LookupOperation { id }).await.unwrap();
+
+        let progress = operation.as_ref().and_then(Operation::progress).unwrap();
+        assert_eq!(Some(40), progress.percent());
+        assert_eq!(Some("import image"), progress.step());
+        assert_eq!(Some("4 of 10 GiB"), progress.message());
+    }
+
+    #[tokio::test]
+    async fn ignore_progress_reported_before_the_end() {
+        let testkit = TestKit::default();
+        let mut actor = testkit
+            .spawn(
+                OPERATION_STATE_MANAGER_NAME,
+                Persistent::new(
+                    OperationStateManagerActor::default(),
+                    Arc::new(MemoryJournal::default()),
+                ),
+            )
+            .await
+            .unwrap();
+        let id: Id = actor
+            .ask(Message::NewOperation { deadline: None })
+            .await
+            .unwrap();
+        let mut sentinel = Sentinel::new(id, actor.mailbox(), Cancellation::new(false).token());
+        sentinel.start().await.unwrap();
+        actor.run_until_idle().await.unwrap();
+
+        sentinel.report(Progress::from_percent(90)).unwrap();
+        sentinel.complete().await.unwrap();
+        let operation: Option<Operation> =
+            actor.ask(Message::LookupOperation { id }).await.unwrap();
+
+        let operation = operation.unwrap();
+        assert_eq!(State::Completed, operation.state());
+        assert!(operation.progress().is_none());
+    }
+
+    #[tokio::test]
+    async fn forget_progress_once_the_operation_ended() {
+        let testkit = TestKit::default();
+        let mut actor = testkit
+            .spawn(
+                OPERATION_STATE_MANAGER_NAME,
+                Persistent::new(
+                    OperationStateManagerActor::default(),
+                    Arc::new(MemoryJournal::default()),
+                ),
+            )
+            .await
+            .unwrap();
+        let id: Id = actor
+            .ask(Message::NewOperation { deadline: None })
+            .await
+            .unwrap();
+        let mut sentinel = Sentinel::new(id, actor.mailbox(), Cancellation::new(false).token());
+        sentinel.start().await.unwrap();
+
+        sentinel.report(Progress::from_percent(90)).unwrap();
+        actor.run_until_idle().await.unwrap();
+        sentinel.complete().await.unwrap();
+        actor.run_until_idle().await.unwrap();
+
+        assert!(actor.actor().actor().progress.is_empty());
+        let operation: Option<Operation> =
+            actor.ask(Message::LookupOperation { id }).await.unwrap();
+        assert!(operation.unwrap().progress().is_none());
+    }
+
+    #[tokio::test]
+    async fn cancel_operation_once_its_worker_confirms() {
+        let op_state = OperationStateManagerHandle::new(&Workspace::default()).unwrap();
+        let id = op_state.new_operation().await.unwrap();
+        let mut sentinel = op_state.new_sentinel(id).await.unwrap();
+        sentinel.start().await.unwrap();
+        assert!(!sentinel.is_cancel_requested());
+
+        let operation = op_state.request_cancel(id).await.unwrap();
+        assert_eq!(State::Canceling, operation.state());
+
+        sentinel.cancel_requested().await;
+        sentinel.cancel().await.unwrap();
+
+        let operation = op_state.lookup_operation(&id).await.unwrap().unwrap();
+        assert_eq!(State::Canceled, operation.state());
+        assert_eq!(
+            State::Canceling,
+            operation.transitions_audits().last().unwrap().from()
+        );
+    }
+
+    #[tokio::test]
+    async fn let_canceling_worker_complete() {
+        let op_state = OperationStateManagerHandle::new(&Workspace::default()).unwrap();
+        let id = op_state.new_operation().await.unwrap();
+        let mut sentinel = op_state.new_sentinel(id).await.unwrap();
+        sentinel.start().await.unwrap();
+        op_state.request_cancel(id).await.unwrap();
+
+        sentinel.complete().await.unwrap();
+
+        let operation = op_state.lookup_operation(&id).await.unwrap().unwrap();
+        assert_eq!(State::Completed, operation.state());
+    }
+
+    #[tokio::test]
+    async fn hand_requested_cancellation_to_new_sentinels() {
+        let op_state = OperationStateManagerHandle::new(&Workspace::default()).unwrap();
+        let id = op_state.new_operation().await.unwrap();
+        let _queued = op_state.new_sentinel(id).await.unwrap();
+        op_state.request_cancel(id).await.unwrap();
+
+        let mut sentinel = op_state.new_sentinel(id).await.unwrap();
+        assert!(sentinel.is_cancel_requested());
+
+        assert!(matches!(
+            sentinel.start().await,
+            Err(OperationError::InvalidTransition {
+                from: State::Canceling,
+                to: State::Working
+            })
+        ));
+        sentinel.cancel().await.unwrap();
+
+        let operation = op_state.lookup_operation(&id).await.unwrap().unwrap();
+        assert_eq!(State::Canceled, operation.state());
+    }
+
+    #[tokio::test]
+    async fn cancel_queued_operation_without_sentinel_right_away() {
+        let op_state = OperationStateManagerHandle::new(&Workspace::default()).unwrap();
+        let id = op_state.new_operation().await.unwrap();
+
+        let operation = op_state.request_cancel(id).await.unwrap();
+
+        assert_eq!(State::Canceled, operation.state());
+        let audits = operation.transitions_audits();
+        let audit = audits.last().unwrap();
+        assert_eq!(State::Queued, audit.from());
+        assert_eq!(Some(CANCELED_WHILE_QUEUED_REASON), audit.reason());
+        assert!(matches!(
+            op_state.request_cancel(id).await,
+            Err(OperationError::InvalidTransition {
+                from: State::Canceled,
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn refuse_to_cancel_finished_or_unknown_operation() {
+        let op_state = OperationStateManagerHandle::new(&Workspace::default()).unwrap();
+        let id = op_state.new_operation().await.unwrap();
+        let mut sentinel = op_state.new_sentinel(id).await.unwrap();
+        sentinel.start().await.unwrap();
+        sentinel.complete().await.unwrap();
+
+        assert!(matches!(
+            op_state.request_cancel(id).await,
+            Err(OperationError::InvalidTransition {
+                from: State::Completed,
+                to: State::Canceling
+            })
+        ));
+        assert!(matches!(
+            op_state.request_cancel(Id::generate()).await,
+            Err(OperationError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn cancel_canceling_operations_interrupted_by_restart() {
+        let journal = Arc::new(MemoryJournal::default());
+        let (workspace, op_state, stopped) = start(journal.clone());
+        let id = op_state.new_operation().await.unwrap();
+        let mut sentinel = op_state.new_sentinel(id).await.unwrap();
+        sentinel.start().await.unwrap();
+        op_state.request_cancel(id).await.unwrap();
+        stop(workspace, stopped).await;
+
+        let op_state = restart(journal).await;
+
+        let operation = op_state.lookup_operation(&id).await.unwrap().unwrap();
+        assert_eq!(State::Canceled, operation.state());
+    }
+
+    #[tokio::test]
+    async fn fail_operation_once_its_sentinels_are_dropped() {
+        let workspace = Workspace::default();
+        let mut transitions = workspace
+            .bus()
+            .subscribe(&OPERATION_TRANSITIONS, Lag::Skip)
+            .unwrap();
+        let op_state = OperationStateManagerHandle::new(&workspace).unwrap();
+        let id = op_state.new_operation().await.unwrap();
+        let mut sentinel = op_state.new_sentinel(id).await.unwrap();
+        sentinel.start().await.unwrap();
+        let clone = sentinel.clone();
+
+        drop(sentinel);
+        let operation = op_state.lookup_operation(&id).await.unwrap().unwrap();
+        assert_eq!(State::Working, operation.state());
+
+        drop(clone);
+        while transitions.recv().await.unwrap().to != State::Failed {}
+
+        let operation = op_state.lookup_operation(&id).await.unwrap().unwrap();
+        assert_eq!(State::Failed, operation.state());
+        assert_eq!(
+            Some(ABANDONED_REASON),
+            operation.transitions_audits().last().unwrap().reason()
+        );
+        assert_eq!(ABANDONED_CODE, operation.failure().unwrap().code());
+    }
+
+    #[tokio::test]
+    async fn forget_queued_operation_once_its_sentinels_are_dropped() {
+        let testkit = TestKit::default();
+        let mut actor = testkit
+            .spawn(
+                OPERATION_STATE_MANAGER_NAME,
+                Persistent::new(
+                    OperationStateManagerActor::default(),
+                    Arc::new(MemoryJournal::default()),
+                ),
+            )
+            .await
+            .unwrap();
+        let id: Id = actor
+            .ask(Message::NewOperation { deadline: None })
+            .await
+            .unwrap();
+        let (state, token) = actor
+            .ask::<NewSentinelReply>(Message::NewSentinel { id })
+            .await
+            .unwrap()
+            .unwrap();
+        let sentinel = Sentinel::reify(id, state, actor.mailbox(), token);
+        assert_eq!(1, actor.actor().actor().workers.len());
+
+        drop(sentinel);
+        tokio::task::yield_now().await;
+        actor.run_until_idle().await.unwrap();
+
+        assert!(actor.actor().actor().workers.is_empty());
+        assert_eq!(State::Queued, actor.actor().state()[&id].state());
+    }
+
+    #[tokio::test]
+    async fn keep_operation_finished_before_its_sentinel_is_dropped() {
+        let op_state = OperationStateManagerHandle::new(&Workspace::default()).unwrap();
+        let id = op_state.new_operation().await.unwrap();
+        let mut sentinel = op_state.new_sentinel(id).await.unwrap();
+        sentinel.start().await.unwrap();
+        sentinel.complete().await.unwrap();
+
+        drop(sentinel);
+
+        let operation = op_state.lookup_operation(&id).await.unwrap().unwrap();
+        assert_eq!(State::Completed, operation.state());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn fail_operation_whose_worker_stopped_heartbeating() {
+        let op_state = OperationStateManagerHandle::new(&Workspace::default()).unwrap();
+        let silent = op_state.new_operation().await.unwrap();
+        let quiet = op_state.new_operation().await.unwrap();
+        let mut silent_sentinel = op_state.new_sentinel(silent).await.unwrap();
+        let mut quiet_sentinel = op_state.new_sentinel(quiet).await.unwrap();
+        silent_sentinel.start().await.unwrap();
+        quiet_sentinel.start().await.unwrap();
+
+        silent_sentinel.heartbeat().unwrap();
+        tokio::time::sleep(HEARTBEAT_TIMEOUT + CHECK_INTERVAL).await;
+
+        let operation = op_state.lookup_operation(&silent).await.unwrap().unwrap();
+        assert_eq!(State::Failed, operation.state());
+        assert_eq!(
+            Some(ABANDONED_REASON),
+            operation.transitions_audits().last().unwrap().reason()
+        );
+        // never sending heartbeats is not stopping.
+        let operation = op_state.lookup_operation(&quiet).await.unwrap().unwrap();
+        assert_eq!(State::Working, operation.state());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn fail_operation_unfinished_at_its_deadline() {
+        let op_state = OperationStateManagerHandle::new(&Workspace::default()).unwrap();
+        let deadline = Utc::now();
+        let working = op_state
+            .new_operation_with_deadline(deadline)
+            .await
+            .unwrap();
+        let queued = op_state
+            .new_operation_with_deadline(deadline)
+            .await
+            .unwrap();
+        let later = op_state
+            .new_operation_with_deadline(deadline + chrono::Duration::hours(1))
+            .await
+            .unwrap();
+        let unbounded = op_state.new_operation().await.unwrap();
+        let mut sentinel = op_state.new_sentinel(working).await.unwrap();
+        sentinel.start().await.unwrap();
+
+        tokio::time::sleep(CHECK_INTERVAL * 2).await;
+
+        assert!(sentinel.is_cancel_requested());
+        let operation = op_state.lookup_operation(&working).await.unwrap().unwrap();
+        assert_eq!(State::Failed, operation.state());
+        assert_eq!(Some(deadline), operation.deadline());
+        assert_eq!(
+            Some(DEADLINE_EXCEEDED_REASON),
+            operation.transitions_audits().last().unwrap().reason()
+        );
+        assert_eq!("DEADLINE_EXCEEDED", operation.failure().unwrap().code());
+        let operation = op_state.lookup_operation(&queued).await.unwrap().unwrap();
+        assert_eq!(State::Failed, operation.state());
+        for id in [later, unbounded] {
+            let operation = op_state.lookup_operation(&id).await.unwrap().unwrap();
+            assert_eq!(State::Queued, operation.state());
+        }
+
+        // the worker confirming late changes nothing.
+        sentinel.cancel().await.unwrap();
+        let operation = op_state.lookup_operation(&working).await.unwrap().unwrap();
+        assert_eq!(State::Failed, operation.state());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn fail_operation_whose_deadline_passed_during_a_restart() {
+        let journal = Arc::new(MemoryJournal::default());
+        let (workspace, op_state, stopped) = start(journal.clone());
+        let id = op_state
+            .new_operation_with_deadline(Utc::now())
+            .await
+            .unwrap();
+        let operation = op_state.lookup_operation(&id).await.unwrap().unwrap();
+        assert_eq!(State::Queued, operation.state());
+        stop(workspace, stopped).await;
+
+        let op_state = restart(journal).await;
+
+        let operation = op_state.lookup_operation(&id).await.unwrap().unwrap();
+        assert_eq!(State::Failed, operation.state());
+        assert_eq!("DEADLINE_EXCEEDED", operation.failure().unwrap().code());
+    }
 }
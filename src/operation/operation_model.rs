@@ -1,42 +1,205 @@
-#![allow(unused)]
 use std::borrow::Cow;
 
 use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 use super::{error::OperationError, states::State, Id};
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TransitionAudit {
     from: State,
     to: State,
     created_at: DateTime<Utc>,
+    reason: Option<String>,
 }
 
 impl TransitionAudit {
-    fn new(from: State, to: State) -> Self {
+    pub(super) fn new(from: State, to: State, reason: Option<String>) -> Self {
         TransitionAudit {
             from,
             to,
             created_at: Local::now().into(),
+            reason,
         }
     }
+
+    pub fn from(&self) -> State {
+        self.from.clone()
+    }
+
+    pub fn to(&self) -> State {
+        self.to.clone()
+    }
+
+    pub fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+
+    /// Why the transition happened, when it was not requested by the worker.
+    pub fn reason(&self) -> Option<&str> {
+        self.reason.as_deref()
+    }
+}
+
+/// Error kept on a failed operation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OperationFailure {
+    /// Stable identifier of the error, like `INTERRUPTED`.
+    code: String,
+    message: String,
+    details: Option<Value>,
+}
+
+impl OperationFailure {
+    pub fn new<C: Into<String>, M: Into<String>>(code: C, message: M) -> Self {
+        OperationFailure {
+            code: code.into(),
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn with_details(mut self, details: Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn details(&self) -> Option<&Value> {
+        self.details.as_ref()
+    }
+}
+
+impl From<OperationError> for OperationFailure {
+    fn from(value: OperationError) -> Self {
+        let code = match value {
+            OperationError::NotFound(_) => "NOT_FOUND",
+            OperationError::InvalidTransition { .. } => "INVALID_TRANSITION",
+            OperationError::Sender | OperationError::Receiver => "CHANNEL",
+            OperationError::NotWorking(_) => "NOT_WORKING",
+            OperationError::InvalidResult(_) => "INVALID_RESULT",
+            OperationError::DeadlineExceeded(_) => "DEADLINE_EXCEEDED",
+            OperationError::Actor(_) => "STATE_MANAGER",
+        };
+
+        OperationFailure::new(code, value.to_string())
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Steps {
+    pub done: u32,
+    pub total: u32,
+}
+
+/// Latest progress reported by the worker of a `Working` operation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Progress {
+    percent: Option<u8>,
+    steps: Option<Steps>,
+    step: Option<String>,
+    message: Option<String>,
+    updated_at: DateTime<Utc>,
+}
+
+impl Progress {
+    /// Progress as a percentage, capped at 100.
+    pub fn from_percent(percent: u8) -> Self {
+        Self::new(Some(percent.min(100)), None)
+    }
+
+    /// Progress as `done` out of `total` steps.
+    pub fn from_steps(done: u32, total: u32) -> Self {
+        Self::new(None, Some(Steps { done, total }))
+    }
+
+    fn new(percent: Option<u8>, steps: Option<Steps>) -> Self {
+        Progress {
+            percent,
+            steps,
+            step: None,
+            message: None,
+            updated_at: Utc::now(),
+        }
+    }
+
+    /// Name of the step being run, like `copy disk`.
+    pub fn with_step<S: Into<String>>(mut self, step: S) -> Self {
+        self.step = Some(step.into());
+        self
+    }
+
+    pub fn with_message<S: Into<String>>(mut self, message: S) -> Self {
+        self.message = Some(message.into());
+        self
+    }
+
+    pub fn percent(&self) -> Option<u8> {
+        self.percent
+    }
+
+    pub fn steps(&self) -> Option<Steps> {
+        self.steps
+    }
+
+    pub fn step(&self) -> Option<&str> {
+        self.step.as_deref()
+    }
+
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+
+    pub fn updated_at(&self) -> DateTime<Utc> {
+        self.updated_at
+    }
+}
+
+/// How an operation ended, reported with its last transition.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(super) enum Outcome {
+    Failed(OperationFailure),
+    Completed(Value),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Operation {
     id: Id,
     created_at: DateTime<Utc>,
+    #[serde(default)]
+    deadline: Option<DateTime<Utc>>,
     state: State,
     transitions_audits: Vec<TransitionAudit>,
+    /// Kept in memory by the state manager, never journaled.
+    #[serde(skip)]
+    progress: Option<Progress>,
+    failure: Option<OperationFailure>,
+    result: Option<Value>,
 }
 
 impl Operation {
-    pub fn new() -> Operation {
+    pub(super) fn new(
+        id: Id,
+        created_at: DateTime<Utc>,
+        deadline: Option<DateTime<Utc>>,
+    ) -> Operation {
         Operation {
-            id: Id::generate(),
-            created_at: Local::now().into(),
+            id,
+            created_at,
+            deadline,
             state: State::Queued,
             transitions_audits: Vec::new(),
+            progress: None,
+            failure: None,
+            result: None,
         }
     }
 
@@ -44,25 +207,50 @@ impl Operation {
         self.id
     }
 
+    pub fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+
+    /// Instant the operation is failed at when still unfinished.
+    pub fn deadline(&self) -> Option<DateTime<Utc>> {
+        self.deadline
+    }
+
     pub fn state(&self) -> State {
         self.state.clone()
     }
 
-    pub fn apply(&mut self, expected: State, new_state: State) -> Result<(), OperationError> {
-        if self.state != expected {
-            return Err(OperationError::StateMismatch {
-                expected,
-                current: self.state.clone(),
-            });
-        }
+    /// Last progress reported while the operation was `Working`, not kept
+    /// across restarts.
+    pub fn progress(&self) -> Option<&Progress> {
+        self.progress.as_ref()
+    }
+
+    pub(super) fn report(&mut self, progress: Progress) {
+        self.progress = Some(progress);
+    }
 
-        let from = self.state.clone();
-        let to = new_state.clone();
+    /// Set once the operation `Failed`.
+    pub fn failure(&self) -> Option<&OperationFailure> {
+        self.failure.as_ref()
+    }
+
+    /// Set when the operation `Completed` with a result.
+    pub fn result(&self) -> Option<&Value> {
+        self.result.as_ref()
+    }
 
-        self.transitions_audits.push(TransitionAudit::new(from, to));
+    /// Moves the operation to the target state of `audit`, the state manager
+    /// checked the transition before recording it.
+    pub(super) fn record(&mut self, audit: TransitionAudit, outcome: Option<Outcome>) {
+        match outcome {
+            Some(Outcome::Failed(failure)) => self.failure = Some(failure),
+            Some(Outcome::Completed(result)) => self.result = Some(result),
+            None => {}
+        }
 
-        self.state = new_state;
-        Ok(())
+        self.state = audit.to();
+        self.transitions_audits.push(audit);
     }
 
     pub fn transitions_audits(&self) -> Cow<Vec<TransitionAudit>> {
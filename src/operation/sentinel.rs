@@ -1,21 +1,139 @@
-use tokio::sync::mpsc::Sender;
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use serde::Serialize;
+use tokio::{task::AbortHandle, time::Instant};
+
+use crate::actor::{
+    mailbox::{Envelop, Mailbox, Priority},
+    ActorError,
+};
+
+use super::{
+    cancellation::CancellationToken,
+    error::OperationError,
+    operation_model::{OperationFailure, Outcome, Progress},
+    states::State,
+    Id, Message, StateManager,
+};
+
+/// Minimum time between two progress updates sent to the state manager.
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+/// Minimum time between two heartbeats sent to the state manager.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Shared by the clones of a sentinel, tells the state manager when the last
+/// one is dropped.
+#[derive(Debug)]
+struct Lease {
+    id: Id,
+    mailbox: Mailbox<StateManager>,
+}
+
+impl Drop for Lease {
+    fn drop(&mut self) {
+        let message = Message::SentinelDropped { id: self.id };
+
+        // the notice waits for room in the mailbox, a lost one would leave the
+        // operation `Working` forever.
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => {
+                let mailbox = self.mailbox.clone();
+                runtime.spawn(async move {
+                    let _ = mailbox.send(message).await;
+                });
+            }
+            Err(_) => {
+                let _ = self.mailbox.try_send(message);
+            }
+        }
+    }
+}
 
-use super::{error::OperationError, states::State, Id, Message};
+/// Progress reports held back by the throttling, shared by the clones of a
+/// sentinel.
+#[derive(Debug, Default)]
+struct Throttle {
+    pending: Option<Progress>,
+    sent_at: Option<Instant>,
+    /// Task sending `pending` once the interval elapsed.
+    flush: Option<AbortHandle>,
+}
+
+impl Throttle {
+    /// Sends the pending progress, it stays pending when the mailbox is full.
+    fn send(&mut self, id: Id, mailbox: &Mailbox<StateManager>) -> Result<(), ActorError> {
+        let Some(progress) = self.pending.take() else {
+            return Ok(());
+        };
+        let message = Message::ReportProgress {
+            id,
+            progress: progress.clone(),
+        };
+
+        match mailbox.try_send_with_envelop(Envelop::with_priority(message, Priority::Low)) {
+            Ok(()) => {
+                self.sent_at = Some(Instant::now());
+                Ok(())
+            }
+            Err(e) => {
+                self.pending = Some(progress);
+                Err(e)
+            }
+        }
+    }
+
+    fn cancel_flush(&mut self) {
+        if let Some(flush) = self.flush.take() {
+            flush.abort();
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct Sentinel {
     id: Id,
     state: State,
-    sender: Sender<Message>,
+    mailbox: Mailbox<StateManager>,
+    cancellation: CancellationToken,
+    throttle: Arc<Mutex<Throttle>>,
+    /// Shared by the clones like the progress throttle.
+    heartbeat_sent_at: Arc<Mutex<Option<Instant>>>,
+    /// Only held for its drop.
+    lease: Arc<Lease>,
 }
 
 impl Sentinel {
-    pub fn new(id: Id, sender: tokio::sync::mpsc::Sender<Message>) -> Self {
-        Self::reify(id, State::Queued, sender)
+    pub(super) fn new(
+        id: Id,
+        mailbox: Mailbox<StateManager>,
+        cancellation: CancellationToken,
+    ) -> Self {
+        Self::reify(id, State::Queued, mailbox, cancellation)
     }
 
-    pub fn reify(id: Id, state: State, sender: tokio::sync::mpsc::Sender<Message>) -> Self {
-        Sentinel { id, state, sender }
+    pub(super) fn reify(
+        id: Id,
+        state: State,
+        mailbox: Mailbox<StateManager>,
+        cancellation: CancellationToken,
+    ) -> Self {
+        let lease = Arc::new(Lease {
+            id,
+            mailbox: mailbox.clone(),
+        });
+
+        Sentinel {
+            id,
+            state,
+            mailbox,
+            cancellation,
+            throttle: Arc::default(),
+            heartbeat_sent_at: Arc::default(),
+            lease,
+        }
     }
 
     pub fn id(&self) -> Id {
@@ -26,52 +144,193 @@ impl Sentinel {
         self.state.clone()
     }
 
+    /// Whether cancellation was requested, the worker should stop and confirm
+    /// with `cancel`. Also set once the operation is failed at its deadline,
+    /// the confirmation is then ignored.
+    pub fn is_cancel_requested(&self) -> bool {
+        self.cancellation.is_cancelled()
+    }
+
+    /// Resolves once cancellation is requested.
+    pub async fn cancel_requested(&self) {
+        self.cancellation.cancelled().await
+    }
+
+    /// Token to hand to the tasks doing the work.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancellation.clone()
+    }
+
     pub async fn start(&mut self) -> Result<(), OperationError> {
-        self.apply(State::Working).await
+        self.apply(State::Working, None).await
     }
 
-    pub async fn fail(&mut self, _error: OperationError) -> Result<(), OperationError> {
-        self.apply(State::Failed).await
+    /// Fails the operation, `failure` is kept on it for the API to report.
+    pub async fn fail<F: Into<OperationFailure>>(
+        &mut self,
+        failure: F,
+    ) -> Result<(), OperationError> {
+        let outcome = Outcome::Failed(failure.into());
+        self.apply(State::Failed, Some(outcome)).await
     }
 
     pub async fn cancel(&mut self) -> Result<(), OperationError> {
-        self.apply(State::Canceled).await
+        self.apply(State::Canceled, None).await
     }
 
     pub async fn complete(&mut self) -> Result<(), OperationError> {
-        self.apply(State::Completed).await
+        self.apply(State::Completed, None).await
+    }
+
+    /// Completes the operation with `result`, like the id of the created VM.
+    pub async fn complete_with<R: Serialize>(&mut self, result: &R) -> Result<(), OperationError> {
+        let result = serde_json::to_value(result)
+            .map_err(|e| OperationError::InvalidResult(e.to_string()))?;
+        self.apply(State::Completed, Some(Outcome::Completed(result)))
+            .await
+    }
+
+    /// Reports the progress of the `Working` operation. Reports made less than
+    /// `PROGRESS_INTERVAL` after the last one sent are held back, the latest
+    /// of them is sent once the interval elapsed.
+    pub fn report(&mut self, progress: Progress) -> Result<(), OperationError> {
+        if self.state != State::Working {
+            return Err(OperationError::NotWorking(self.state.clone()));
+        }
+
+        let mut throttle = self.throttle.lock().unwrap();
+        throttle.pending = Some(progress);
+        if throttle.flush.is_some() {
+            return Ok(());
+        }
+
+        let now = Instant::now();
+        let next = throttle
+            .sent_at
+            .map(|sent_at| sent_at + PROGRESS_INTERVAL)
+            .filter(|next| *next > now);
+        if let Some(next) = next {
+            self.schedule_flush(&mut throttle, next);
+            return Ok(());
+        }
+
+        // progress is not worth waiting for room in the mailbox, it is
+        // retried once the interval elapsed.
+        match throttle.send(self.id, &self.mailbox) {
+            Ok(()) => Ok(()),
+            Err(ActorError::MailboxFull) => {
+                self.schedule_flush(&mut throttle, now + PROGRESS_INTERVAL);
+                Ok(())
+            }
+            Err(e) => Err(e.into()),
+        }
     }
 
-    async fn transition(&mut self, new_state: State) -> Result<(), OperationError> {
+    fn schedule_flush(&self, throttle: &mut Throttle, at: Instant) {
+        let shared = self.throttle.clone();
+        let (id, mailbox) = (self.id, self.mailbox.clone());
+
+        let flush = tokio::spawn(async move {
+            let mut at = at;
+            loop {
+                tokio::time::sleep_until(at).await;
+
+                let mut throttle = shared.lock().unwrap();
+                match throttle.send(id, &mailbox) {
+                    Err(ActorError::MailboxFull) => at = Instant::now() + PROGRESS_INTERVAL,
+                    _ => {
+                        throttle.flush = None;
+                        return;
+                    }
+                }
+            }
+        });
+        throttle.flush = Some(flush.abort_handle());
+    }
+
+    /// Tells the state manager the worker of the `Working` operation is alive.
+    /// Once a worker sent a heartbeat, the operation is failed as abandoned
+    /// when they stop. Heartbeats less than `HEARTBEAT_INTERVAL` apart are
+    /// dropped.
+    pub fn heartbeat(&mut self) -> Result<(), OperationError> {
+        if self.state != State::Working {
+            return Err(OperationError::NotWorking(self.state.clone()));
+        }
+
+        let now = Instant::now();
+        let mut heartbeat_sent_at = self.heartbeat_sent_at.lock().unwrap();
+        if heartbeat_sent_at.is_some_and(|sent_at| now.duration_since(sent_at) < HEARTBEAT_INTERVAL)
+        {
+            return Ok(());
+        }
+
+        // a heartbeat dropped by a full mailbox is retried on the next call.
+        let message = Message::Heartbeat { id: self.id };
+        match self
+            .mailbox
+            .try_send_with_envelop(Envelop::with_priority(message, Priority::Low))
+        {
+            Ok(()) => {
+                *heartbeat_sent_at = Some(now);
+                Ok(())
+            }
+            Err(ActorError::MailboxFull) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn transition(
+        &mut self,
+        new_state: State,
+        outcome: Option<Outcome>,
+    ) -> Result<(), OperationError> {
         let from = self.state.clone();
         let to = new_state.clone();
 
-        self.communicate_changes(from, to).await?;
+        self.communicate_changes(from, to, outcome).await?;
         self.state = new_state;
 
+        // progress reported before the end is ignored by the state manager.
+        let mut throttle = self.throttle.lock().unwrap();
+        throttle.pending = None;
+        throttle.cancel_flush();
+
         Ok(())
     }
 
-    async fn communicate_changes(&self, from: State, to: State) -> Result<(), OperationError> {
+    async fn communicate_changes(
+        &self,
+        from: State,
+        to: State,
+        outcome: Option<Outcome>,
+    ) -> Result<(), OperationError> {
         let message = Message::UpdateOperation {
             id: self.id,
             from,
             to,
+            outcome,
         };
 
-        self.sender.send(message).await?;
+        self.mailbox.send(message).await?;
         Ok(())
     }
 
-    async fn apply(&mut self, new_state: State) -> Result<(), OperationError> {
+    async fn apply(
+        &mut self,
+        new_state: State,
+        outcome: Option<Outcome>,
+    ) -> Result<(), OperationError> {
         match (self.state.clone(), new_state.clone()) {
-            (State::Queued, State::Working) => self.transition(new_state).await,
-            (State::Queued, State::Canceled) => self.transition(new_state).await,
+            (State::Queued, State::Working) => self.transition(new_state, outcome).await,
+            (State::Queued, State::Canceled) => self.transition(new_state, outcome).await,
 
             // transition to terminal states.
-            (State::Working, State::Failed) => self.transition(new_state).await,
-            (State::Working, State::Canceled) => self.transition(new_state).await,
-            (State::Working, State::Completed) => self.transition(new_state).await,
+            (State::Working, State::Failed) => self.transition(new_state, outcome).await,
+            (State::Working, State::Canceled) => self.transition(new_state, outcome).await,
+            (State::Working, State::Completed) => self.transition(new_state, outcome).await,
+            (State::Canceling, State::Canceled | State::Failed | State::Completed) => {
+                self.transition(new_state, outcome).await
+            }
             _ => Err(OperationError::InvalidTransition {
                 from: self.state.clone(),
                 to: new_state,
@@ -82,65 +341,61 @@ impl Sentinel {
 
 #[cfg(test)]
 mod test {
-    use tokio::sync::mpsc::Receiver;
+    use crate::{
+        actor::{
+            mailbox::make_mailbox,
+            testkit::{Probe, TestKit},
+        },
+        operation::{cancellation::Cancellation, OPERATION_STATE_MANAGER_NAME},
+    };
 
     use super::*;
 
-    fn sentinel() -> (Id, Receiver<Message>, Sentinel) {
-        let (tx, rx) = tokio::sync::mpsc::channel(1);
-        let id = Id::generate();
-        let sentinel = Sentinel::new(id, tx);
-        (id, rx, sentinel)
+    fn sentinel() -> (Id, Probe<StateManager>, Sentinel) {
+        sentinel_reify(State::Queued)
     }
 
-    fn sentinel_reify(state: State) -> (Id, Receiver<Message>, Sentinel) {
-        let (tx, rx) = tokio::sync::mpsc::channel(1);
+    fn sentinel_reify(state: State) -> (Id, Probe<StateManager>, Sentinel) {
+        let (tx, probe) = TestKit::default().probe(OPERATION_STATE_MANAGER_NAME);
         let id = Id::generate();
-        let sentinel = Sentinel::reify(id, state, tx);
-        (id, rx, sentinel)
+        let sentinel = Sentinel::reify(id, state, tx, Cancellation::new(false).token());
+        (id, probe, sentinel)
+    }
+
+    fn assert_update(probe: &mut Probe<StateManager>, id: Id, from: State, to: State) {
+        match probe.expect() {
+            Message::UpdateOperation {
+                id: updated,
+                from: old,
+                to: new,
+                ..
+            } => assert_eq!((id, from, to), (updated, old, new)),
+            message => panic!("unexpected message {:?}", message),
+        }
     }
 
     #[tokio::test]
     async fn valid_from_queued_to_start() {
-        let (id, mut rx, mut sentinel) = sentinel();
-
-        let handle = tokio::spawn(async move {
-            assert!(matches!(
-                rx.recv().await.unwrap(),
-                Message::UpdateOperation {
-                    id,
-                    from: State::Queued,
-                    to: State::Working,
-                }
-            ))
-        });
+        let (id, mut probe, mut sentinel) = sentinel();
 
-        sentinel.start().await;
-        handle.await;
+        sentinel.start().await.unwrap();
+
+        assert_update(&mut probe, id, State::Queued, State::Working);
+        probe.expect_none();
     }
 
     #[tokio::test]
     async fn valid_from_queued_to_cancel() {
-        let (id, mut rx, mut sentinel) = sentinel();
-
-        let handle = tokio::spawn(async move {
-            assert!(matches!(
-                rx.recv().await.unwrap(),
-                Message::UpdateOperation {
-                    id,
-                    from: State::Queued,
-                    to: State::Canceled,
-                }
-            ))
-        });
+        let (id, mut probe, mut sentinel) = sentinel();
 
-        sentinel.cancel().await;
-        handle.await;
+        sentinel.cancel().await.unwrap();
+
+        assert_update(&mut probe, id, State::Queued, State::Canceled);
     }
 
     #[tokio::test]
     async fn invalid_from_queued_to_failed() {
-        let (id, mut rx, mut sentinel) = sentinel();
+        let (id, mut probe, mut sentinel) = sentinel();
 
         assert!(matches!(
             sentinel.fail(OperationError::Sender).await,
@@ -149,11 +404,12 @@ mod test {
                 to: State::Failed
             })
         ));
+        probe.expect_none();
     }
 
     #[tokio::test]
     async fn invalid_from_queued_to_complete() {
-        let (id, mut rx, mut sentinel) = sentinel();
+        let (id, mut probe, mut sentinel) = sentinel();
 
         assert!(matches!(
             sentinel.complete().await,
@@ -162,98 +418,51 @@ mod test {
                 to: State::Completed
             })
         ));
+        probe.expect_none();
     }
 
     #[tokio::test]
     async fn valid_from_working_to_cancel() {
-        let (id, mut rx, mut sentinel) = sentinel();
-
-        let handle = tokio::spawn(async move {
-            assert!(matches!(
-                rx.recv().await.unwrap(),
-                Message::UpdateOperation {
-                    id,
-                    from: State::Queued,
-                    to: State::Working,
-                }
-            ));
-
-            assert!(matches!(
-                rx.recv().await.unwrap(),
-                Message::UpdateOperation {
-                    id,
-                    from: State::Working,
-                    to: State::Canceled,
-                }
-            ))
-        });
+        let (id, mut probe, mut sentinel) = sentinel();
 
-        sentinel.start().await;
-        sentinel.cancel().await;
-        handle.await;
+        sentinel.start().await.unwrap();
+        sentinel.cancel().await.unwrap();
+
+        assert_update(&mut probe, id, State::Queued, State::Working);
+        assert_update(&mut probe, id, State::Working, State::Canceled);
     }
 
     #[tokio::test]
     async fn valid_from_working_to_failed() {
-        let (id, mut rx, mut sentinel) = sentinel();
-
-        let handle = tokio::spawn(async move {
-            assert!(matches!(
-                rx.recv().await.unwrap(),
-                Message::UpdateOperation {
-                    id,
-                    from: State::Queued,
-                    to: State::Working,
-                }
-            ));
-
-            assert!(matches!(
-                rx.recv().await.unwrap(),
-                Message::UpdateOperation {
-                    id,
-                    from: State::Working,
-                    to: State::Failed,
-                }
-            ))
-        });
-
-        sentinel.start().await;
-        sentinel.fail(OperationError::Sender).await;
-        handle.await;
+        let (id, mut probe, mut sentinel) = sentinel();
+
+        sentinel.start().await.unwrap();
+        sentinel.fail(OperationError::Sender).await.unwrap();
+
+        assert_update(&mut probe, id, State::Queued, State::Working);
+        match probe.expect() {
+            Message::UpdateOperation {
+                outcome: Some(Outcome::Failed(failure)),
+                ..
+            } => assert_eq!("CHANNEL", failure.code()),
+            message => panic!("unexpected message {:?}", message),
+        }
     }
 
     #[tokio::test]
     async fn valid_from_working_to_complete() {
-        let (id, mut rx, mut sentinel) = sentinel();
-
-        let handle = tokio::spawn(async move {
-            assert!(matches!(
-                rx.recv().await.unwrap(),
-                Message::UpdateOperation {
-                    id,
-                    from: State::Queued,
-                    to: State::Working,
-                }
-            ));
-
-            assert!(matches!(
-                rx.recv().await.unwrap(),
-                Message::UpdateOperation {
-                    id,
-                    from: State::Working,
-                    to: State::Completed,
-                }
-            ))
-        });
+        let (id, mut probe, mut sentinel) = sentinel();
+
+        sentinel.start().await.unwrap();
+        sentinel.complete().await.unwrap();
 
-        sentinel.start().await;
-        sentinel.complete().await;
-        handle.await;
+        assert_update(&mut probe, id, State::Queued, State::Working);
+        assert_update(&mut probe, id, State::Working, State::Completed);
     }
 
     #[tokio::test]
     async fn invalid_from_complete_to_fail() {
-        let (id, mut rx, mut sentinel) = sentinel_reify(State::Completed);
+        let (id, mut probe, mut sentinel) = sentinel_reify(State::Completed);
 
         assert!(matches!(
             sentinel.fail(OperationError::Sender).await,
@@ -266,7 +475,7 @@ mod test {
 
     #[tokio::test]
     async fn invalid_from_complete_to_cancel() {
-        let (id, mut rx, mut sentinel) = sentinel_reify(State::Completed);
+        let (id, mut probe, mut sentinel) = sentinel_reify(State::Completed);
 
         assert!(matches!(
             sentinel.cancel().await,
@@ -279,10 +488,151 @@ mod test {
 
     #[tokio::test]
     async fn reify_with_initial_state() {
-        let (tx, rx) = tokio::sync::mpsc::channel(1);
+        let (tx, rx) = make_mailbox();
         let id = Id::generate();
-        let sentinel = Sentinel::reify(id, State::Failed, tx);
+        let sentinel = Sentinel::reify(id, State::Failed, tx, Cancellation::new(false).token());
 
         assert_eq!(State::Failed, sentinel.state());
     }
+
+    fn reported(probe: &mut Probe<StateManager>) -> Vec<Progress> {
+        probe
+            .received()
+            .into_iter()
+            .map(|message| match message {
+                Message::ReportProgress { progress, .. } => progress,
+                message => panic!("unexpected message {:?}", message),
+            })
+            .collect()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn throttle_progress_reports() {
+        let (_, mut probe, mut sentinel) = sentinel_reify(State::Working);
+
+        for percent in [10, 20, 30] {
+            sentinel.report(Progress::from_percent(percent)).unwrap();
+        }
+        assert_eq!(
+            vec![Some(10)],
+            reported(&mut probe)
+                .iter()
+                .map(Progress::percent)
+                .collect::<Vec<_>>()
+        );
+
+        // the latest held back report is sent once the interval elapsed.
+        tokio::time::sleep(PROGRESS_INTERVAL + Duration::from_millis(1)).await;
+        assert_eq!(
+            vec![Some(30)],
+            reported(&mut probe)
+                .iter()
+                .map(Progress::percent)
+                .collect::<Vec<_>>()
+        );
+
+        sentinel
+            .report(Progress::from_steps(2, 5).with_step("copy disk"))
+            .unwrap();
+        probe.expect_none();
+        tokio::time::sleep(PROGRESS_INTERVAL).await;
+
+        let progress = reported(&mut probe);
+        assert_eq!(1, progress.len());
+        assert_eq!(Some("copy disk"), progress[0].step());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn drop_held_back_progress_once_finished() {
+        let (id, mut probe, mut sentinel) = sentinel_reify(State::Working);
+
+        sentinel.report(Progress::from_percent(10)).unwrap();
+        sentinel.report(Progress::from_percent(20)).unwrap();
+        sentinel.complete().await.unwrap();
+        tokio::time::sleep(PROGRESS_INTERVAL * 2).await;
+
+        assert_update(&mut probe, id, State::Working, State::Completed);
+        assert!(matches!(
+            probe.expect(),
+            Message::ReportProgress { progress, .. } if progress.percent() == Some(10)
+        ));
+        probe.expect_none();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn throttle_heartbeats() {
+        let (id, mut probe, mut sentinel) = sentinel_reify(State::Working);
+
+        sentinel.heartbeat().unwrap();
+        sentinel.heartbeat().unwrap();
+        tokio::time::advance(HEARTBEAT_INTERVAL).await;
+        sentinel.heartbeat().unwrap();
+
+        let heartbeats = probe.received();
+        assert_eq!(2, heartbeats.len());
+        assert!(heartbeats.iter().all(
+            |message| matches!(message, Message::Heartbeat { id: beating } if *beating == id)
+        ));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn throttle_heartbeats_across_clones() {
+        let (_, mut probe, mut sentinel) = sentinel_reify(State::Working);
+        let mut clone = sentinel.clone();
+
+        sentinel.heartbeat().unwrap();
+        clone.heartbeat().unwrap();
+
+        assert_eq!(1, probe.received().len());
+    }
+
+    #[tokio::test]
+    async fn notify_when_the_last_clone_is_dropped() {
+        let (id, mut probe, sentinel) = sentinel();
+        let clone = sentinel.clone();
+
+        drop(sentinel);
+        tokio::task::yield_now().await;
+        probe.expect_none();
+
+        drop(clone);
+        tokio::task::yield_now().await;
+        assert!(matches!(
+            probe.expect(),
+            Message::SentinelDropped { id: dropped } if dropped == id
+        ));
+    }
+
+    #[tokio::test]
+    async fn notify_drop_once_the_mailbox_has_room() {
+        let (id, mut probe, sentinel) = sentinel();
+        while sentinel.mailbox.try_send(Message::Quit).is_ok() {}
+
+        drop(sentinel);
+        tokio::task::yield_now().await;
+        while let Some(message) = probe.try_recv() {
+            assert!(matches!(message, Message::Quit));
+        }
+        tokio::task::yield_now().await;
+
+        assert!(matches!(
+            probe.expect(),
+            Message::SentinelDropped { id: dropped } if dropped == id
+        ));
+    }
+
+    #[tokio::test]
+    async fn refuse_progress_unless_working() {
+        let (_, mut probe, mut sentinel) = sentinel();
+
+        assert!(matches!(
+            sentinel.report(Progress::from_percent(10)),
+            Err(OperationError::NotWorking(State::Queued))
+        ));
+        assert!(matches!(
+            sentinel.heartbeat(),
+            Err(OperationError::NotWorking(State::Queued))
+        ));
+        probe.expect_none();
+    }
 }
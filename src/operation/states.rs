@@ -1,7 +1,11 @@
-#[derive(Debug, Clone, PartialEq)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum State {
     Queued,
     Working,
+    /// Cancellation was requested, the worker has yet to stop.
+    Canceling,
     Failed,
     Canceled,
     Completed,
@@ -12,9 +16,17 @@ impl std::fmt::Display for State {
         match self {
             State::Queued => write!(f, "queued"),
             State::Working => write!(f, "working"),
+            State::Canceling => write!(f, "canceling"),
             State::Failed => write!(f, "failed"),
             State::Canceled => write!(f, "canceled"),
             State::Completed => write!(f, "completed"),
         }
     }
 }
+
+impl State {
+    /// Whether the operation ended, no transition leaves these states.
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, State::Failed | State::Canceled | State::Completed)
+    }
+}
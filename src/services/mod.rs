@@ -1,21 +1,88 @@
-#[derive(Debug, Clone, Default)]
-pub struct OperationService {}
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+
+use crate::{
+    actor::{journal::Journal, metrics::ActorMetricsSnapshot, Workspace},
+    operation::{Id, Operation, OperationError, OperationStateManagerHandle, Sentinel},
+};
+
+#[derive(Debug, Clone)]
+pub struct OperationService {
+    state_manager: OperationStateManagerHandle,
+}
 
 impl OperationService {
-    pub fn new() -> Self {
-        Self {}
+    /// Spawns the operation state manager in `workspace`, operations are
+    /// kept in memory.
+    pub fn new(workspace: &Workspace) -> Result<Self, OperationError> {
+        Ok(Self {
+            state_manager: OperationStateManagerHandle::new(workspace)?,
+        })
+    }
+
+    /// Same as `new` with operations recorded in `journal`.
+    pub fn with_journal(
+        workspace: &Workspace,
+        journal: Arc<dyn Journal>,
+    ) -> Result<Self, OperationError> {
+        Ok(Self {
+            state_manager: OperationStateManagerHandle::with_journal(workspace, journal)?,
+        })
+    }
+
+    pub async fn create(&self) -> Result<Id, OperationError> {
+        self.state_manager.new_operation().await
     }
 
-    pub fn find(&self, id: &str) -> Option<String> {
-        if id == "111" {
-            Some(id.to_string())
-        } else {
-            None
+    /// Creates an operation failed when still unfinished at `deadline`, its
+    /// worker is told to stop through its cancellation token.
+    pub async fn create_with_deadline(
+        &self,
+        deadline: DateTime<Utc>,
+    ) -> Result<Id, OperationError> {
+        self.state_manager
+            .new_operation_with_deadline(deadline)
+            .await
+    }
+
+    pub async fn find(&self, id: Id) -> Result<Option<Operation>, OperationError> {
+        self.state_manager.lookup_operation(&id).await
+    }
+
+    /// Requests the cancellation of the operation `id`, it stays `Canceling`
+    /// until its worker confirms. A `Queued` operation no worker took is
+    /// canceled right away.
+    pub async fn cancel(&self, id: Id) -> Result<Operation, OperationError> {
+        self.state_manager.request_cancel(id).await
+    }
+
+    /// Returns the sentinel reporting the progress of the operation `id`.
+    pub async fn sentinel(&self, id: Id) -> Result<Sentinel, OperationError> {
+        self.state_manager.new_sentinel(id).await
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ActorService {
+    workspace: Workspace,
+}
+
+impl ActorService {
+    pub fn new(workspace: &Workspace) -> Self {
+        Self {
+            workspace: workspace.clone(),
         }
     }
+
+    /// Runtime metrics of every live actor of the workspace.
+    pub fn metrics(&self) -> Vec<ActorMetricsSnapshot> {
+        self.workspace.metrics()
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct ServiceRegistry {
     pub operation_service: OperationService,
+    pub actor_service: ActorService,
 }
@@ -0,0 +1,47 @@
+use netheril::{
+    actor::Workspace,
+    api::router,
+    services::{ActorService, OperationService, ServiceRegistry},
+};
+use serde::Deserialize;
+
+use crate::common::api_server;
+
+#[derive(Debug, Deserialize)]
+struct QueueResponse {
+    normal: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct MetricsResponse {
+    name: String,
+    status: String,
+    queue: QueueResponse,
+}
+
+#[tokio::test]
+async fn it_should_return_the_metrics_of_live_actors() {
+    let workspace = Workspace::default();
+    let operation_service = OperationService::new(&workspace).unwrap();
+    operation_service.create().await.unwrap();
+
+    let router = router().with_state(ServiceRegistry {
+        operation_service,
+        actor_service: ActorService::new(&workspace),
+    });
+    let (_server, client) = api_server(router).await;
+
+    let response: Vec<MetricsResponse> = client
+        .get("/api/actors/metrics")
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(response.len(), 1);
+    assert_eq!(response[0].name, "operation-state-manager");
+    assert_eq!(response[0].status, "RUNNING");
+    assert_eq!(response[0].queue.normal, 0);
+}
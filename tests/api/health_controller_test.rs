@@ -1,7 +1,8 @@
 use netheril::{
+    actor::Workspace,
     api::router,
     domains::health::{HealthView, State},
-    services::{OperationService, ServiceRegistry},
+    services::{ActorService, OperationService, ServiceRegistry},
 };
 
 use crate::common::api_server;
@@ -9,7 +10,8 @@ use crate::common::api_server;
 #[tokio::test]
 async fn it_should_return_health_status() {
     let services = ServiceRegistry {
-        operation_service: OperationService::new(),
+        operation_service: OperationService::new(&Workspace::default()).unwrap(),
+        actor_service: ActorService::new(&Workspace::default()),
     };
 
     let router = router().with_state(services);
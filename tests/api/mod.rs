@@ -1,2 +1,4 @@
+mod actors_controller_test;
 mod health_controller_test;
+mod operations_controller_test;
 mod root_controller_test;
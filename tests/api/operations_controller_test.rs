@@ -0,0 +1,206 @@
+use netheril::{
+    actor::Workspace,
+    api::router,
+    operation::OperationFailure,
+    services::{ActorService, OperationService, ServiceRegistry},
+};
+use reqwest::StatusCode;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::common::api_server;
+
+#[derive(Debug, Deserialize)]
+struct TransitionResponse {
+    from: String,
+    to: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Response {
+    operation_id: String,
+    status: String,
+    transitions: Vec<TransitionResponse>,
+    failure: Option<FailureResponse>,
+    result: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FailureResponse {
+    code: String,
+    message: String,
+}
+
+#[tokio::test]
+async fn it_should_return_the_operation_state_and_transitions() {
+    let operation_service = OperationService::new(&Workspace::default()).unwrap();
+    let id = operation_service.create().await.unwrap();
+    let mut sentinel = operation_service.sentinel(id).await.unwrap();
+    sentinel.start().await.unwrap();
+
+    let router = router().with_state(ServiceRegistry {
+        operation_service: operation_service.clone(),
+        actor_service: ActorService::new(&Workspace::default()),
+    });
+    let (_server, client) = api_server(router).await;
+
+    let response: Response = client
+        .get(format!("/api/operations/{}", id).as_str())
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(response.operation_id, id.to_string());
+    assert_eq!(response.status, "WORKING");
+    assert_eq!(response.transitions.len(), 1);
+    assert_eq!(response.transitions[0].from, "QUEUED");
+    assert_eq!(response.transitions[0].to, "WORKING");
+    assert!(response.failure.is_none());
+    assert!(response.result.is_none());
+}
+
+#[tokio::test]
+async fn it_should_return_the_result_of_a_completed_operation() {
+    let operation_service = OperationService::new(&Workspace::default()).unwrap();
+    let id = operation_service.create().await.unwrap();
+    let mut sentinel = operation_service.sentinel(id).await.unwrap();
+    sentinel.start().await.unwrap();
+    sentinel
+        .complete_with(&json!({ "vm_id": "vm-1" }))
+        .await
+        .unwrap();
+
+    let router = router().with_state(ServiceRegistry {
+        operation_service: operation_service.clone(),
+        actor_service: ActorService::new(&Workspace::default()),
+    });
+    let (_server, client) = api_server(router).await;
+
+    let response: Response = client
+        .get(format!("/api/operations/{}", id).as_str())
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status, "COMPLETED");
+    assert_eq!(response.result, Some(json!({ "vm_id": "vm-1" })));
+}
+
+#[tokio::test]
+async fn it_should_return_the_failure_of_a_failed_operation() {
+    let operation_service = OperationService::new(&Workspace::default()).unwrap();
+    let id = operation_service.create().await.unwrap();
+    let mut sentinel = operation_service.sentinel(id).await.unwrap();
+    sentinel.start().await.unwrap();
+    sentinel
+        .fail(OperationFailure::new("DISK_FULL", "no space left"))
+        .await
+        .unwrap();
+
+    let router = router().with_state(ServiceRegistry {
+        operation_service: operation_service.clone(),
+        actor_service: ActorService::new(&Workspace::default()),
+    });
+    let (_server, client) = api_server(router).await;
+
+    let response: Response = client
+        .get(format!("/api/operations/{}", id).as_str())
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    let failure = response.failure.unwrap();
+    assert_eq!(response.status, "FAILED");
+    assert_eq!(failure.code, "DISK_FULL");
+    assert_eq!(failure.message, "no space left");
+}
+
+#[tokio::test]
+async fn it_should_return_not_found_for_an_unknown_operation() {
+    let services = ServiceRegistry {
+        operation_service: OperationService::new(&Workspace::default()).unwrap(),
+        actor_service: ActorService::new(&Workspace::default()),
+    };
+
+    let router = router().with_state(services);
+    let (_server, client) = api_server(router).await;
+
+    let response = client
+        .get("/api/operations/67e55044-10b1-426f-9247-bb680e5fe0c8")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn it_should_reject_a_malformed_operation_id() {
+    let services = ServiceRegistry {
+        operation_service: OperationService::new(&Workspace::default()).unwrap(),
+        actor_service: ActorService::new(&Workspace::default()),
+    };
+
+    let router = router().with_state(services);
+    let (_server, client) = api_server(router).await;
+
+    let response = client.get("/api/operations/111").send().await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn it_should_request_the_cancellation_of_an_operation() {
+    let operation_service = OperationService::new(&Workspace::default()).unwrap();
+    let id = operation_service.create().await.unwrap();
+    let mut sentinel = operation_service.sentinel(id).await.unwrap();
+    sentinel.start().await.unwrap();
+
+    let router = router().with_state(ServiceRegistry {
+        operation_service: operation_service.clone(),
+        actor_service: ActorService::new(&Workspace::default()),
+    });
+    let (_server, client) = api_server(router).await;
+
+    let response = client
+        .post(format!("/api/operations/{}/cancel", id).as_str())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+    let response: Response = response.json().await.unwrap();
+    assert_eq!(response.status, "CANCELING");
+    assert!(sentinel.is_cancel_requested());
+}
+
+#[tokio::test]
+async fn it_should_refuse_to_cancel_a_finished_operation() {
+    let operation_service = OperationService::new(&Workspace::default()).unwrap();
+    let id = operation_service.create().await.unwrap();
+    let mut sentinel = operation_service.sentinel(id).await.unwrap();
+    sentinel.cancel().await.unwrap();
+
+    let router = router().with_state(ServiceRegistry {
+        operation_service: operation_service.clone(),
+        actor_service: ActorService::new(&Workspace::default()),
+    });
+    let (_server, client) = api_server(router).await;
+
+    let response = client
+        .post(format!("/api/operations/{}/cancel", id).as_str())
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+}
@@ -1,6 +1,7 @@
 use netheril::{
+    actor::Workspace,
     api::router,
-    services::{OperationService, ServiceRegistry},
+    services::{ActorService, OperationService, ServiceRegistry},
     version::BUILD,
 };
 use serde::Deserialize;
@@ -23,7 +24,8 @@ async fn it_should_return_the_build_information() {
     }
 
     let services = ServiceRegistry {
-        operation_service: OperationService::new(),
+        operation_service: OperationService::new(&Workspace::default()).unwrap(),
+        actor_service: ActorService::new(&Workspace::default()),
     };
 
     let router = router().with_state(services);
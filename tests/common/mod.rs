@@ -20,6 +20,11 @@ impl Client {
         self.client.get(url)
     }
 
+    pub fn post<R: Into<RelativeUrl>>(&self, path: R) -> RequestBuilder {
+        let url = self.base_url(path.into());
+        self.client.post(url)
+    }
+
     pub fn base_url(&self, path: RelativeUrl) -> String {
         format!("http://{}:{}{}", self.addr.ip(), self.addr.port(), path)
     }